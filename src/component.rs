@@ -26,9 +26,11 @@
 //!    lifetime bindings between those types and the [`Invoker`](Invoker), preventing the latter
 //!    from being reused too early.
 
-use super::descriptor::AsDescriptor;
+use super::computer::AclLister;
+use super::descriptor::{AsDescriptor, Decoded, Owned};
 use super::error::{Error, Result};
-use super::helpers::{call_buffer_len, call_buffer_str, call_string};
+use super::helpers::{call_buffer_len, call_buffer_str, call_string, check_written};
+use super::sys::component as sys;
 use super::Address;
 use crate::panic_or_trap;
 use core::convert::TryFrom;
@@ -36,7 +38,12 @@ use core::fmt::{Display, Formatter};
 use core::marker::PhantomData;
 use core::num::NonZeroUsize;
 use core::ptr;
-use oc_wasm_sys::component as sys;
+use minicbor::data::Type;
+use minicbor::decode::{Decode, Decoder};
+use minicbor::encode::{Encode, Encoder};
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
 
 /// An object that is capable of listing components attached to the computer.
 ///
@@ -49,6 +56,8 @@ use oc_wasm_sys::component as sys;
 /// access the lister at a time.
 pub struct Lister(());
 
+singleton!(LISTER: Lister = Lister(()));
+
 impl Lister {
 	/// Returns the lister.
 	///
@@ -56,10 +65,12 @@ impl Lister {
 	/// subsequent calls, it will return `None`.
 	#[must_use = "A Lister can only be taken once. It needs to be saved. Discarding it means it is impossible to ever list components."]
 	pub fn take() -> Option<Self> {
-		static mut INSTANCE: Option<Lister> = Some(Self(()));
-		// SAFETY: Wasm doesn’t have threads, so only one caller can get here at a time, and the
-		// Option will be empty for all but the first caller.
-		unsafe { INSTANCE.take() }
+		take_singleton!(LISTER)
+	}
+
+	/// Gives the lister back, so that the next call to [`take`](Lister::take) returns it again.
+	pub(crate) fn restore(self) {
+		restore_singleton!(LISTER, self);
 	}
 
 	/// Begins listing the components attached to the computer.
@@ -72,24 +83,139 @@ impl Lister {
 	/// fail should be impossible due to the type system.
 	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
 	pub fn start<'lister>(&'lister mut self, component_type: Option<&str>) -> Listing<'lister> {
-		let result =
-			// SAFETY: list_start permits null or a string pointer/length pair.
-			unsafe{call_string(sys::list_start, component_type)};
-		// Can’t fail because list_start can only fail due to MemoryFault or StringDecode, and
-		// Error::from_i32 already treats those as unreachable.
-		result.unwrap_or_else(|_| panic_or_trap!("unreachable"));
+		list_start(component_type);
 		Listing(PhantomData)
 	}
+
+	/// Counts the components attached to the computer.
+	///
+	/// The `component_type` parameter, if present, restricts the count to only components of the
+	/// specified type. If the parameter is absent, all components are counted.
+	///
+	/// This performs a complete listing, but does not fetch any component types, so no buffer is
+	/// needed.
+	///
+	/// # Panics
+	/// This function panics if the underlying syscall fails, because the only reasons it could
+	/// fail should be impossible due to the type system.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn count(&mut self, component_type: Option<&str>) -> usize {
+		let mut listing = self.start(component_type);
+		let mut count = 0;
+		while listing.next().is_some() {
+			count += 1;
+		}
+		count
+	}
+
+	/// Checks whether any component of a given type is attached to the computer.
+	///
+	/// The `component_type` parameter is the type of component to look for.
+	///
+	/// This stops as soon as the first matching component is found, so it is cheaper than
+	/// checking whether [`count`](Lister::count) is nonzero.
+	///
+	/// # Panics
+	/// This function panics if the underlying syscall fails, because the only reasons it could
+	/// fail should be impossible due to the type system.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn exists_any(&mut self, component_type: &str) -> bool {
+		self.start(Some(component_type)).next().is_some()
+	}
+
+	/// Collects the addresses of the components attached to the computer into a slice.
+	///
+	/// The `component_type` parameter, if present, restricts the collection to only components of
+	/// the specified type. If the parameter is absent, all components are collected. The `out`
+	/// parameter is where the addresses are written, starting at the beginning.
+	///
+	/// The number of addresses written is returned, along with a flag that is `true` if there were
+	/// more components than fit in `out` (in which case the extra components are not included) or
+	/// `false` if all components were collected.
+	///
+	/// # Panics
+	/// This function panics if the underlying syscall fails, because the only reasons it could
+	/// fail should be impossible due to the type system.
+	pub fn collect_into(
+		&mut self,
+		component_type: Option<&str>,
+		out: &mut [Address],
+	) -> (usize, bool) {
+		let mut listing = self.start(component_type);
+		let mut count = 0;
+		for slot in out {
+			match listing.next() {
+				Some(entry) => *slot = *entry.address(),
+				None => return (count, false),
+			}
+			count += 1;
+		}
+		(count, listing.next().is_some())
+	}
+
+	/// Lists the components attached to the computer as [`Component`](Component) values.
+	///
+	/// The `component_type` parameter, if present, restricts the listing to only return components
+	/// of the specified type. If the parameter is absent, all components are returned.
+	///
+	/// Because a `Component` holds only an address, it does not borrow from the listing, so unlike
+	/// [`start`](Lister::start), this can return a real iterator.
+	///
+	/// # Panics
+	/// This function and the returned iterator panic if the underlying syscalls fail, because the
+	/// only reasons they could fail should be impossible due to the type system.
+	pub fn components<'lister>(
+		&'lister mut self,
+		component_type: Option<&str>,
+	) -> impl Iterator<Item = Component> + 'lister {
+		let mut listing = self.start(component_type);
+		core::iter::from_fn(move || listing.next().map(|entry| Component::new(*entry.address())))
+	}
+}
+
+/// Begins listing the components attached to the computer.
+///
+/// The `component_type` parameter, if present, restricts the listing to only return components of
+/// the specified type.
+///
+/// # Panics
+/// This function panics if the underlying syscall fails, because the only reasons it could fail
+/// should be impossible due to the type system.
+fn list_start(component_type: Option<&str>) {
+	let result =
+		// SAFETY: list_start permits null or a string pointer/length pair.
+		unsafe{call_string(sys::list_start, component_type)};
+	// Can’t fail because list_start can only fail due to MemoryFault or StringDecode, and
+	// Error::from_i32 already treats those as unreachable.
+	result.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e));
 }
 
 /// An in-progress component listing.
 ///
 /// The `'lister` lifetime parameter is the lifetime of the component lister that is performing the
 /// listing.
+///
+/// A listing does not remember the component type it was started with. To repeat it, pass the
+/// same component type to [`restart`](Listing::restart).
 #[must_use = "Starting a component listing is only useful if you read the results."]
 pub struct Listing<'lister>(PhantomData<&'lister mut Lister>);
 
 impl<'lister> Listing<'lister> {
+	/// Restarts the listing from the beginning.
+	///
+	/// The `component_type` parameter, if present, restricts the restarted listing to only return
+	/// components of the specified type, as for [`Lister::start`](Lister::start); pass the same
+	/// value as was originally passed to repeat the same listing. This allows a listing that has
+	/// been exhausted to be repeated without going back to the [`Lister`](Lister).
+	///
+	/// # Panics
+	/// This function panics if the underlying syscall fails, because the only reasons it could
+	/// fail should be impossible due to the type system.
+	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
+	pub fn restart(&mut self, component_type: Option<&str>) {
+		list_start(component_type);
+	}
+
 	/// Returns the next entry in the list of components.
 	///
 	/// If there is a next entry, its UUID is return. If not, `None` is returned.
@@ -100,14 +226,28 @@ impl<'lister> Listing<'lister> {
 	/// * This function panics if there is a mismatch between OC-Wasm-safe’s and OpenComputers’s
 	///   ideas of the length or formatting of a component address.
 	#[allow(clippy::should_implement_trait)] // It’s very like Iterator::next, but can’t be due to lifetimes.
-	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
 	pub fn next<'listing>(&'listing mut self) -> Option<ListEntry<'listing, 'lister>> {
+		// Can’t fail because list_next can only fail due to MemoryFault, which, because we provide
+		// it with a valid buffer, is impossible.
+		self.try_next()
+			.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e))
+	}
+
+	/// Returns the next entry in the list of components, reporting syscall failures.
+	///
+	/// This is the same as [`next`](Listing::next), except that if the underlying syscall fails,
+	/// the error is returned instead of panicking. Such failures should be impossible, but this
+	/// can help to diagnose a mismatch between OC-Wasm-Safe and the version of OC-Wasm in use.
+	///
+	/// # Errors
+	/// * [`Unknown`](Error::Unknown) or another error is returned if the underlying syscall fails.
+	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
+	pub fn try_next<'listing>(&'listing mut self) -> Result<Option<ListEntry<'listing, 'lister>>> {
 		// SAFETY: list_next permits a writeable buffer pointer and promises to always write a
-		// valid UUID. It can only fail due to MemoryFault, which, because we provide it with a
-		// valid buffer, is impossible. However we care whether its return value is 0 or 1.
+		// valid UUID.
 		let mut buf = uuid::Bytes::default();
-		let rc = unsafe { sys::list_next(buf.as_mut_ptr()) };
-		if rc == 0 {
+		let rc = Error::from_i32(unsafe { sys::list_next(buf.as_mut_ptr()) })?;
+		Ok(if rc == 0 {
 			None
 		} else {
 			let address = Address::from_bytes(buf);
@@ -115,7 +255,7 @@ impl<'lister> Listing<'lister> {
 				address,
 				listing: PhantomData,
 			})
-		}
+		})
 	}
 }
 
@@ -144,14 +284,28 @@ impl ListEntry<'_, '_> {
 	/// # Panics
 	/// * This function panics if the underlying syscall fails, because the only reasons it could
 	///   fail should be impossible due to the type system.
-	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
 	#[must_use = "This function is only useful for its return value"]
 	pub fn type_name_len(&self) -> NonZeroUsize {
+		self.try_type_name_len()
+			.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e))
+	}
+
+	/// Returns the length, in bytes, of the component’s type, reporting syscall failures.
+	///
+	/// This is the same as [`type_name_len`](ListEntry::type_name_len), except that if the
+	/// underlying syscall fails, the error is returned instead of panicking. Such failures should
+	/// be impossible, but this can help to diagnose a mismatch between OC-Wasm-Safe and the
+	/// version of OC-Wasm in use.
+	///
+	/// # Errors
+	/// * [`Unknown`](Error::Unknown) or another error is returned if the underlying syscall fails.
+	/// * [`Other`](Error::Other) is returned if the syscall reports a zero length, which a
+	///   component type can never have.
+	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
+	pub fn try_type_name_len(&self) -> Result<NonZeroUsize> {
 		// SAFETY: list_type permits null.
-		let len = unsafe { call_buffer_len(sys::list_type) }
-			.unwrap_or_else(|_| panic_or_trap!("unreachable"));
-		// SAFETY: A component type can’t be empty.
-		unsafe { NonZeroUsize::new_unchecked(len) }
+		let len = unsafe { call_buffer_len(sys::list_type) }?;
+		NonZeroUsize::new(len).ok_or(Error::Other)
 	}
 
 	/// Returns the type of the most recently listed component.
@@ -216,6 +370,7 @@ pub fn component_type<'buf>(address: &Address, buffer: &'buf mut [u8]) -> Result
 			unsafe { sys::component_type(address.as_ptr(), buf_ptr, buf_len) },
 		)?
 	};
+	check_written(bytes_written, buffer.len());
 	Ok(
 		// SAFETY: component_type promises to always write a valid UTF-8 string and return its
 		// length.
@@ -223,6 +378,33 @@ pub fn component_type<'buf>(address: &Address, buffer: &'buf mut [u8]) -> Result
 	)
 }
 
+/// Returns the type of a component, checking the buffer size first.
+///
+/// The `address` parameter identifies the component by its UUID. The `buffer` parameter identifies
+/// where to store the component type.
+///
+/// This function first obtains the length of the component type, as by
+/// [`component_type_len`](component_type_len). If `buffer` is large enough, the type is written
+/// into it and a string slice referring to it is returned. If not, nothing is written, and the
+/// needed buffer size is returned instead, so that the caller can obtain a suitably sized buffer
+/// (for example, by slicing a larger stack array) and try again.
+///
+/// # Errors
+/// * [`NoSuchComponent`](Error::NoSuchComponent) is returned if the component does not exist or is
+///   inaccessible.
+#[must_use = "This function is only useful for its return value"]
+pub fn component_type_into_stack<'buf>(
+	address: &Address,
+	buffer: &'buf mut [u8],
+) -> Result<core::result::Result<&'buf mut str, NonZeroUsize>> {
+	let len = component_type_len(address)?;
+	if buffer.len() < len.get() {
+		Ok(Err(len))
+	} else {
+		component_type(address, buffer).map(Ok)
+	}
+}
+
 /// Returns the slot that a component is installed into.
 ///
 /// The `address` parameter identifies the component by its UUID.
@@ -251,6 +433,8 @@ pub fn slot(address: &Address) -> Result<u32> {
 /// access the lister at a time.
 pub struct MethodLister(());
 
+singleton!(METHOD_LISTER: MethodLister = MethodLister(()));
+
 impl MethodLister {
 	/// Returns the lister.
 	///
@@ -258,10 +442,13 @@ impl MethodLister {
 	/// subsequent calls, it will return `None`.
 	#[must_use = "A Lister can only be taken once. It needs to be saved. Discarding it means it is impossible to ever list methods."]
 	pub fn take() -> Option<Self> {
-		static mut INSTANCE: Option<MethodLister> = Some(Self(()));
-		// SAFETY: Wasm doesn’t have threads, so only one caller can get here at a time, and the
-		// Option will be empty for all but the first caller.
-		unsafe { INSTANCE.take() }
+		take_singleton!(METHOD_LISTER)
+	}
+
+	/// Gives the lister back, so that the next call to [`take`](MethodLister::take) returns it
+	/// again.
+	pub(crate) fn restore(self) {
+		restore_singleton!(METHOD_LISTER, self);
 	}
 
 	/// Begins iteration over the methods available on a component.
@@ -303,6 +490,8 @@ impl MethodLister {
 
 /// The possible attributes of a method.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct MethodAttributes {
 	/// The method is direct.
 	///
@@ -325,12 +514,57 @@ pub struct MethodAttributes {
 	pub setter: bool,
 }
 
+impl MethodAttributes {
+	/// The bit in the raw attribute bitmask that indicates a direct method.
+	pub const DIRECT: u32 = 1;
+
+	/// The bit in the raw attribute bitmask that indicates a property getter.
+	pub const GETTER: u32 = 2;
+
+	/// The bit in the raw attribute bitmask that indicates a property setter.
+	pub const SETTER: u32 = 4;
+
+	/// Returns the raw attribute bitmask, as reported by OC-Wasm.
+	///
+	/// This is the inverse of the `From<u32>` conversion, for bits that are meaningful.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn bits(self) -> u32 {
+		(if self.direct { Self::DIRECT } else { 0 })
+			| (if self.getter { Self::GETTER } else { 0 })
+			| (if self.setter { Self::SETTER } else { 0 })
+	}
+}
+
 impl From<u32> for MethodAttributes {
 	fn from(value: u32) -> Self {
 		Self {
-			direct: (value & 1) != 0,
-			getter: (value & 2) != 0,
-			setter: (value & 4) != 0,
+			direct: (value & Self::DIRECT) != 0,
+			getter: (value & Self::GETTER) != 0,
+			setter: (value & Self::SETTER) != 0,
+		}
+	}
+}
+
+impl From<MethodAttributes> for u32 {
+	fn from(value: MethodAttributes) -> Self {
+		value.bits()
+	}
+}
+
+impl Display for MethodAttributes {
+	/// Formats the attributes as space-separated words.
+	///
+	/// `direct` is written first if the method is direct, followed by `getter` and/or `setter`,
+	/// or `action` if the method is neither a getter nor a setter.
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		if self.direct {
+			f.write_str("direct ")?;
+		}
+		match (self.getter, self.setter) {
+			(false, false) => f.write_str("action"),
+			(true, false) => f.write_str("getter"),
+			(false, true) => f.write_str("setter"),
+			(true, true) => f.write_str("getter setter"),
 		}
 	}
 }
@@ -346,16 +580,31 @@ impl MethodListing<'_> {
 	/// # Panics
 	/// This function panics if the underlying syscall fails, because the only reasons it could
 	/// fail should be impossible due to the type system.
-	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
 	#[must_use = "This function is only useful for its return value"]
 	pub fn next_len(&self) -> Option<NonZeroUsize> {
-		let result = Error::from_isize(
-			// SAFETY: methods_next permits null for both pointers.
-			unsafe { sys::methods_next(ptr::null_mut(), 0, ptr::null_mut()) },
-		);
 		// Can’t fail because methods_next can only fail due to MemoryFault or StringDecode, and
 		// Error::from_isize already treats those as unreachable.
-		NonZeroUsize::new(result.unwrap_or_else(|_| panic_or_trap!("unreachable")))
+		self.try_next_len()
+			.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e))
+	}
+
+	/// Returns the length, in bytes, of the name of the next method in the list of methods,
+	/// reporting syscall failures.
+	///
+	/// This is the same as [`next_len`](MethodListing::next_len), except that if the underlying
+	/// syscall fails, the error is returned instead of panicking. Such failures should be
+	/// impossible, but this can help to diagnose a mismatch between OC-Wasm-Safe and the version of
+	/// OC-Wasm in use.
+	///
+	/// # Errors
+	/// * [`Unknown`](Error::Unknown) or another error is returned if the underlying syscall fails.
+	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
+	pub fn try_next_len(&self) -> Result<Option<NonZeroUsize>> {
+		let len = Error::from_isize(
+			// SAFETY: methods_next permits null for both pointers.
+			unsafe { sys::methods_next(ptr::null_mut(), 0, ptr::null_mut()) },
+		)?;
+		Ok(NonZeroUsize::new(len))
 	}
 
 	/// Returns the next method in the list of methods.
@@ -389,6 +638,7 @@ impl MethodListing<'_> {
 		if bytes_written == 0 {
 			Ok(None)
 		} else {
+			check_written(bytes_written, buffer.len());
 			Ok(Some((
 				// SAFETY: methods_next promises to always write a valid UTF-8 string and return its
 				// length.
@@ -399,6 +649,95 @@ impl MethodListing<'_> {
 			)))
 		}
 	}
+
+	/// Advances through the list of methods until a method with a given name is found.
+	///
+	/// The `buffer` parameter is used to hold each method name while it is compared. The `name`
+	/// parameter is the name of the method to look for.
+	///
+	/// If the method is found, its attributes are returned, and the iteration is left positioned
+	/// just after it. If not, `None` is returned, and the list is exhausted.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the name of a method encountered before the one being sought.
+	///
+	/// On error, the iteration does not advance past the method whose name did not fit.
+	pub fn find(&mut self, buffer: &mut [u8], name: &str) -> Result<Option<MethodAttributes>> {
+		while let Some((candidate, attributes)) = self.next(buffer)? {
+			if candidate == name {
+				return Ok(Some(attributes));
+			}
+		}
+		Ok(None)
+	}
+
+	/// Returns the method `n` places further on in the list of methods.
+	///
+	/// The `buffer` parameter identifies where to store the method names. The `n` parameter is the
+	/// number of methods to skip; `nth(buffer, 0)` is equivalent to [`next`](Self::next).
+	///
+	/// If there are more than `n` remaining methods, the skipped methods are discarded, and the
+	/// name and attributes of the following one are returned as by [`next`](Self::next). If not,
+	/// `None` is returned, and the list is exhausted.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the name of one of the methods.
+	///
+	/// On error, the iteration does not advance past the method whose name did not fit.
+	pub fn nth<'buffer>(
+		&mut self,
+		buffer: &'buffer mut [u8],
+		n: usize,
+	) -> Result<Option<(&'buffer mut str, MethodAttributes)>> {
+		for _ in 0..n {
+			if self.next(buffer)?.is_none() {
+				return Ok(None);
+			}
+		}
+		self.next(buffer)
+	}
+
+	/// Counts the remaining methods in the list, consuming the listing.
+	///
+	/// The `buffer` parameter is used to hold each method name while the listing is advanced;
+	/// OC-Wasm only advances a method listing when a name is copied out, so there is no way to
+	/// skip a method without a buffer large enough to hold its name.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the name of one of the methods.
+	pub fn count(mut self, buffer: &mut [u8]) -> Result<usize> {
+		let mut count = 0;
+		while self.next(buffer)?.is_some() {
+			count += 1;
+		}
+		Ok(count)
+	}
+}
+
+/// Returns the attributes of a method on a component.
+///
+/// The `lister` parameter is used to list the component’s methods. The `address` parameter
+/// identifies the component by its UUID. The `method` parameter identifies the method by its name.
+/// The `buffer` parameter is used to hold each method name while it is compared.
+///
+/// If the method exists, its attributes are returned, which can be used (for example) to check
+/// whether it is a getter before calling it. If not, `None` is returned.
+///
+/// # Errors
+/// * [`NoSuchComponent`](Error::NoSuchComponent) is returned if the component does not exist or is
+///   inaccessible.
+/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to hold
+///   the name of a method encountered before the one being sought.
+pub fn method_attributes(
+	lister: &mut MethodLister,
+	address: &Address,
+	method: &str,
+	buffer: &mut [u8],
+) -> Result<Option<MethodAttributes>> {
+	lister.start_component(address)?.find(buffer, method)
 }
 
 /// Returns the length, in bytes, of the documentation for a method on a component.
@@ -468,6 +807,7 @@ pub fn documentation_component<'buf>(
 			},
 		)?
 	};
+	check_written(bytes_written, buffer.len());
 	Ok(
 		// SAFETY: documentation_component promises to always write a valid UTF-8 string and return
 		// its length.
@@ -534,6 +874,7 @@ pub fn documentation_value<'buf>(
 			},
 		)?
 	};
+	check_written(bytes_written, buffer.len());
 	Ok(
 		// SAFETY: documentation_value promises to always write a valid UTF-8 string and return its
 		// length.
@@ -552,6 +893,8 @@ pub fn documentation_value<'buf>(
 /// access the invoker at a time.
 pub struct Invoker(());
 
+singleton!(INVOKER: Invoker = Invoker(()));
+
 impl Invoker {
 	/// Returns the invoker.
 	///
@@ -559,10 +902,13 @@ impl Invoker {
 	/// subsequent calls, it will return `None`.
 	#[must_use = "An Invoker can only be taken once. It needs to be saved. Discarding it means it is impossible to ever make a method call."]
 	pub fn take() -> Option<Self> {
-		static mut INSTANCE: Option<Invoker> = Some(Self(()));
-		// SAFETY: Wasm doesn’t have threads, so only one caller can get here at a time, and the
-		// Option will be empty for all but the first caller.
-		unsafe { INSTANCE.take() }
+		take_singleton!(INVOKER)
+	}
+
+	/// Gives the invoker back, so that the next call to [`take`](Invoker::take) returns it
+	/// again.
+	pub(crate) fn restore(self) {
+		restore_singleton!(INVOKER, self);
 	}
 
 	/// Starts invoking a method on a component.
@@ -585,14 +931,14 @@ impl Invoker {
 		method: &str,
 		params: Option<&[u8]>,
 	) -> Result<(InvokeResult, MethodCall<'invoker>)> {
-		let address = address.as_bytes();
+		let address_bytes = address.as_bytes();
 		let params_ptr = params.map_or(ptr::null(), <[u8]>::as_ptr);
 		let done = Error::from_i32(
 			// SAFETY: invoke_component_method permits an input UUID pointer, method name
 			// pointer/length pair, and CBOR pointer which may be null.
 			unsafe {
 				sys::invoke_component_method(
-					address.as_ptr(),
+					address_bytes.as_ptr(),
 					method.as_ptr(),
 					method.len(),
 					params_ptr,
@@ -605,10 +951,124 @@ impl Invoker {
 			} else {
 				InvokeResult::Incomplete
 			},
-			MethodCall(PhantomData),
+			MethodCall::started(CallTarget::ComponentMethod(*address), method),
 		))
 	}
 
+	/// Invokes a method on a component and fetches its result if it completes immediately.
+	///
+	/// The `address`, `method`, and `params` parameters are as for
+	/// [`component_method`](Invoker::component_method). The `buffer` parameter identifies where to
+	/// store the CBOR-encoded result.
+	///
+	/// If the call completes immediately (as calls to direct methods usually do), the result is
+	/// written into `buffer` and its length is returned. If not, the call is cancelled and `None`
+	/// is returned; the caller should retry with the full
+	/// [`component_method`](Invoker::component_method) flow.
+	///
+	/// If the result does not fit in `buffer`, it is discarded. The method has already run by
+	/// then, so calling it again to retry with a larger buffer repeats any side effects it has. To
+	/// be able to fetch the result of such a method again after a short buffer, use
+	/// [`component_method`](Invoker::component_method) and [`MethodCall::end`](MethodCall::end)
+	/// instead, which hand back the [`MethodCall`](MethodCall) on
+	/// [`BufferTooShort`](InvokeEndResult::BufferTooShort).
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the result, in which case the result is discarded.
+	/// * Any error that [`component_method`](Invoker::component_method) or
+	///   [`MethodCall::end`](MethodCall::end) can return.
+	pub fn component_method_now(
+		&mut self,
+		address: &Address,
+		method: &str,
+		params: Option<&[u8]>,
+		buffer: &mut [u8],
+	) -> Result<Option<usize>> {
+		let (result, call) = self.component_method(address, method, params)?;
+		if result.is_incomplete() {
+			return Ok(None);
+		}
+		match call.end(buffer) {
+			InvokeEndResult::Done(result) => Ok(Some(result?)),
+			InvokeEndResult::BufferTooShort(_) => Err(Error::BufferTooShort),
+			InvokeEndResult::Pending(_) => Ok(None),
+		}
+	}
+
+	/// Invokes a method on a component and decodes its first return value.
+	///
+	/// The `address`, `method`, and `params` parameters are as for
+	/// [`component_method`](Invoker::component_method). The `buffer` parameter identifies where to
+	/// store the CBOR-encoded result while it is decoded.
+	///
+	/// This starts the call and immediately tries to fetch the result. Because the environment is
+	/// cooperative, a call that does not complete immediately (such as any call to an indirect
+	/// method) cannot complete until `run` returns, so in that case the call is cancelled and
+	/// [`QueueEmpty`](Error::QueueEmpty) is returned; the caller should retry with the full
+	/// [`component_method`](Invoker::component_method) flow or on the next timeslice.
+	///
+	/// # Errors
+	/// * [`QueueEmpty`](Error::QueueEmpty) is returned if the method call does not complete
+	///   immediately.
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the result.
+	/// * [`CborDecode`](Error::CborDecode) is returned if the method returned no values or its
+	///   first return value cannot be decoded as a `T`.
+	/// * Any error that [`component_method`](Invoker::component_method) or
+	///   [`MethodCall::end`](MethodCall::end) can return.
+	pub fn call_component_into<'buffer, T: Decode<'buffer, ()>>(
+		&mut self,
+		address: &Address,
+		method: &str,
+		params: Option<&[u8]>,
+		buffer: &'buffer mut [u8],
+	) -> Result<T> {
+		let (_, call) = self.component_method(address, method, params)?;
+		let len = call.end(buffer).expect_done()?;
+		decode_first_result(&buffer[..len])
+	}
+
+	/// Invokes a method on a component and decodes its first return value, allocating a buffer
+	/// for the result.
+	///
+	/// This is the same as [`call_component_into`](Invoker::call_component_into), except that the
+	/// result is fetched into a newly allocated buffer of the right size, so `T` must not borrow
+	/// from it. Owned types such as `String` can be decoded.
+	///
+	/// # Example
+	/// ```no_run
+	/// use oc_wasm_safe::component::Invoker;
+	/// use oc_wasm_safe::Address;
+	///
+	/// # fn example(address: &Address) -> oc_wasm_safe::error::Result<()> {
+	/// let mut invoker = Invoker::take().unwrap();
+	/// let slots: i64 = invoker.call_component(address, "getInventorySize", None)?;
+	/// let label: String = invoker.call_component(address, "getLabel", None)?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	///
+	/// # Errors
+	/// * [`QueueEmpty`](Error::QueueEmpty) is returned if the method call does not complete
+	///   immediately.
+	/// * [`CborDecode`](Error::CborDecode) is returned if the method returned no values or its
+	///   first return value cannot be decoded as a `T`.
+	/// * Any error that [`component_method`](Invoker::component_method) or
+	///   [`MethodCall::end`](MethodCall::end) can return.
+	#[cfg(feature = "alloc")]
+	pub fn call_component<T: for<'buffer> Decode<'buffer, ()>>(
+		&mut self,
+		address: &Address,
+		method: &str,
+		params: Option<&[u8]>,
+	) -> Result<T> {
+		let (_, call) = self.component_method(address, method, params)?;
+		// Most results are small; a larger one only costs an extra length query.
+		let result = call.end_grow(64)?;
+		decode_first_result(&result)
+	}
+
 	/// Starts invoking a callable opaque value.
 	///
 	/// The `descriptor` parameter identifies the opaque value by its descriptor. The `params`
@@ -638,7 +1098,7 @@ impl Invoker {
 			} else {
 				InvokeResult::Incomplete
 			},
-			MethodCall(PhantomData),
+			MethodCall::started(CallTarget::Value(descriptor.as_descriptor().as_raw()), ""),
 		))
 	}
 
@@ -674,10 +1134,88 @@ impl Invoker {
 			} else {
 				InvokeResult::Incomplete
 			},
-			MethodCall(PhantomData),
+			MethodCall::started(
+				CallTarget::ValueIndexedRead(descriptor.as_descriptor().as_raw()),
+				"",
+			),
 		))
 	}
 
+	/// Starts reading from an index of an opaque value using a single key.
+	///
+	/// The `descriptor` parameter identifies the opaque value by its descriptor. The `key`
+	/// parameter is the index to read, typically an integer or a string. The `scratch` parameter is
+	/// a buffer used to hold the encoded parameters.
+	///
+	/// This is equivalent to calling [`value_indexed_read`](Self::value_indexed_read) with a
+	/// one-element parameter array containing `key`.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `scratch` is not large enough to
+	///   hold the encoded parameters.
+	/// * [`CborDecode`](Error::CborDecode) is returned if `key` fails to encode.
+	/// * [`BadDescriptor`](Error::BadDescriptor) is returned if `key` contains a descriptor
+	///   reference to a descriptor that is not open.
+	/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the descriptor table is
+	///   too full and some descriptors must be closed before another method call can be made.
+	pub fn index_read<'invoker, K: Encode<()>>(
+		&'invoker mut self,
+		descriptor: &impl AsDescriptor,
+		key: &K,
+		scratch: &mut [u8],
+	) -> Result<(InvokeResult, MethodCall<'invoker>)> {
+		let mut encoder = Encoder::new(&mut *scratch);
+		encoder
+			.array(1)
+			.and_then(|e| e.encode(key))
+			.map_err(|e| encode_params_error(&e))?;
+		self.value_indexed_read(descriptor, Some(scratch))
+	}
+
+	/// Reads successive indices of an opaque value and collects the results.
+	///
+	/// The `descriptor` parameter identifies the opaque value by its descriptor. The `make_index`
+	/// parameter is called with successive integers starting from zero; each time, it returns
+	/// either a CBOR-encoded array of parameters to use for indexing, or `None` to stop reading.
+	///
+	/// On success, the CBOR-encoded results of the reads are returned, one per index, in order.
+	///
+	/// This function can only be used on opaque values whose indexed reads complete immediately;
+	/// there is no way to wait for a call to finish without returning from `run`.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if an index produced by `make_index`
+	///   contains an invalid or unsupported CBOR sequence.
+	/// * [`BadDescriptor`](Error::BadDescriptor) is returned if the parameters contain a
+	///   descriptor reference to a descriptor that is not open.
+	/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the descriptor table is
+	///   too full and some descriptors must be closed before another method call can be made.
+	/// * [`QueueEmpty`](Error::QueueEmpty) is returned if a read does not complete immediately.
+	/// * [`BadParameters`](Error::BadParameters) or [`Other`](Error::Other) is returned if a read
+	///   fails.
+	///
+	/// On error, the results of any reads that have already completed are discarded.
+	#[cfg(feature = "alloc")]
+	pub fn read_all_indices(
+		&mut self,
+		descriptor: &impl AsDescriptor,
+		mut make_index: impl FnMut(usize) -> Option<Vec<u8>>,
+	) -> Result<Vec<Vec<u8>>> {
+		let mut ret = Vec::new();
+		while let Some(params) = make_index(ret.len()) {
+			let (_, call) = self.value_indexed_read(descriptor, Some(&params))?;
+			let (len, call) = match call.end_length() {
+				InvokeEndLengthResult::Done(result) => result?,
+				InvokeEndLengthResult::Pending(_) => return Err(Error::QueueEmpty),
+			};
+			let mut buffer = vec![0; len];
+			let len = call.end(&mut buffer).expect_done()?;
+			buffer.truncate(len);
+			ret.push(buffer);
+		}
+		Ok(ret)
+	}
+
 	/// Starts writing to an index of an opaque value.
 	///
 	/// The `descriptor` parameter identifies the opaque value by its descriptor. The `params`
@@ -711,10 +1249,46 @@ impl Invoker {
 			} else {
 				InvokeResult::Incomplete
 			},
-			MethodCall(PhantomData),
+			MethodCall::started(
+				CallTarget::ValueIndexedWrite(descriptor.as_descriptor().as_raw()),
+				"",
+			),
 		))
 	}
 
+	/// Starts writing to an index of an opaque value using a single key.
+	///
+	/// The `descriptor` parameter identifies the opaque value by its descriptor. The `key`
+	/// parameter is the index to write, typically an integer or a string. The `value` parameter is
+	/// the value to store. The `scratch` parameter is a buffer used to hold the encoded parameters.
+	///
+	/// This is equivalent to calling [`value_indexed_write`](Self::value_indexed_write) with a
+	/// two-element parameter array containing `key` and `value`.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `scratch` is not large enough to
+	///   hold the encoded parameters.
+	/// * [`CborDecode`](Error::CborDecode) is returned if `key` or `value` fails to encode.
+	/// * [`BadDescriptor`](Error::BadDescriptor) is returned if `key` or `value` contains a
+	///   descriptor reference to a descriptor that is not open.
+	/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the descriptor table is
+	///   too full and some descriptors must be closed before another method call can be made.
+	pub fn index_write<'invoker, K: Encode<()>, V: Encode<()>>(
+		&'invoker mut self,
+		descriptor: &impl AsDescriptor,
+		key: &K,
+		value: &V,
+		scratch: &mut [u8],
+	) -> Result<(InvokeResult, MethodCall<'invoker>)> {
+		let mut encoder = Encoder::new(&mut *scratch);
+		encoder
+			.array(2)
+			.and_then(|e| e.encode(key))
+			.and_then(|e| e.encode(value))
+			.map_err(|e| encode_params_error(&e))?;
+		self.value_indexed_write(descriptor, Some(scratch))
+	}
+
 	/// Starts invoking a method on an opaque value.
 	///
 	/// The `descriptor` parameter identifies the opaque value by its descriptor. The `method`
@@ -754,7 +1328,10 @@ impl Invoker {
 			} else {
 				InvokeResult::Incomplete
 			},
-			MethodCall(PhantomData),
+			MethodCall::started(
+				CallTarget::ValueMethod(descriptor.as_descriptor().as_raw()),
+				method,
+			),
 		))
 	}
 }
@@ -774,8 +1351,38 @@ impl PartialEq for Invoker {
 
 impl Eq for Invoker {}
 
+/// Decodes the first element of a CBOR-encoded method call result.
+///
+/// # Errors
+/// * [`CborDecode`](Error::CborDecode) is returned if `result` is not an array, is empty, or its
+///   first element cannot be decoded as a `T`.
+fn decode_first_result<'buffer, T: Decode<'buffer, ()>>(result: &'buffer [u8]) -> Result<T> {
+	let mut decoder = Decoder::new(result);
+	if decoder.array()? == Some(0) {
+		return Err(Error::CborDecode);
+	}
+	Ok(decoder.decode()?)
+}
+
+/// Converts an error from encoding method call parameters into a scratch buffer.
+///
+/// A write error means the encoder ran out of room, which is reported as
+/// [`BufferTooShort`](Error::BufferTooShort). Any other error comes from a parameter’s
+/// [`Encode`](Encode) implementation refusing to encode its value, which is reported as
+/// [`CborDecode`](Error::CborDecode), because the parameter is not something that can be
+/// represented in CBOR.
+fn encode_params_error<E>(e: &minicbor::encode::Error<E>) -> Error {
+	if e.is_write() {
+		Error::BufferTooShort
+	} else {
+		Error::CborDecode
+	}
+}
+
 /// The possible results of a successful start to a method call.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum InvokeResult {
 	/// The method is complete and its result can be fetched immediately.
 	Complete,
@@ -784,16 +1391,61 @@ pub enum InvokeResult {
 	Incomplete,
 }
 
-/// An in-progress method call.
-///
-/// The `'invoker` lifetime parameter is the lifetime of the method invoker that is performing the
-/// call.
-///
-/// If a value of this type is dropped, the method call is cancelled. If it has not executed yet,
-/// it will not execute; if it has already executed, its result is discarded.
-#[derive(Debug, Eq, PartialEq)]
-#[must_use = "Discarding a MethodCall immediately is buggy. Even if you know the method you are calling is direct and don’t need its return value, direct methods must be run indirectly if the method call cost limit is reached, so you still need to make sure it finishes."]
-pub struct MethodCall<'invoker>(PhantomData<&'invoker mut Invoker>);
+impl InvokeResult {
+	/// Returns whether the method call finished synchronously.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn is_complete(self) -> bool {
+		matches!(self, Self::Complete)
+	}
+
+	/// Returns whether the method call is still running and will finish on a later timeslice.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn is_incomplete(self) -> bool {
+		matches!(self, Self::Incomplete)
+	}
+}
+
+impl Display for InvokeResult {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		f.write_str(match self {
+			Self::Complete => "complete",
+			Self::Incomplete => "incomplete",
+		})
+	}
+}
+
+/// An in-progress method call.
+///
+/// The `'invoker` lifetime parameter is the lifetime of the method invoker that is performing the
+/// call.
+///
+/// If a value of this type is dropped, the method call is cancelled. If it has not executed yet,
+/// it will not execute; if it has already executed, its result is discarded.
+#[derive(Debug, Eq, PartialEq)]
+#[must_use = "Discarding a MethodCall immediately is buggy. Even if you know the method you are calling is direct and don’t need its return value, direct methods must be run indirectly if the method call cost limit is reached, so you still need to make sure it finishes."]
+pub struct MethodCall<'invoker> {
+	/// Ties the call to the invoker that started it.
+	invoker: PhantomData<&'invoker mut Invoker>,
+
+	/// What was called.
+	#[cfg(feature = "debug-calls")]
+	context: CallContext,
+}
+
+impl MethodCall<'_> {
+	/// Creates a `MethodCall` for a call that has just been started.
+	///
+	/// The `target` and `method` parameters identify what was called. They are only recorded if
+	/// the `debug-calls` feature is enabled.
+	#[cfg_attr(not(feature = "debug-calls"), allow(unused_variables))]
+	fn started(target: CallTarget, method: &str) -> Self {
+		Self {
+			invoker: PhantomData,
+			#[cfg(feature = "debug-calls")]
+			context: CallContext::new(target, method),
+		}
+	}
+}
 
 impl<'invoker> MethodCall<'invoker> {
 	/// Returns the length, in bytes, of the result of the method call, or an indication that the
@@ -877,6 +1529,84 @@ impl<'invoker> MethodCall<'invoker> {
 			other => InvokeEndResult::Done(other),
 		}
 	}
+
+	/// Returns the result of the method call as a CBOR-encoded data item in a newly allocated
+	/// buffer.
+	///
+	/// The `initial` parameter is the size of the buffer to try first. If the result fits, it is
+	/// fetched with a single system call; otherwise, the exact length is queried and the fetch is
+	/// retried with a buffer of that size. Choosing an `initial` size that most results fit into
+	/// therefore avoids the separate length query in the common case.
+	///
+	/// # Errors
+	/// * [`QueueEmpty`](MethodCallError::QueueEmpty) is returned if the method call is not
+	///   finished, in which case it is cancelled.
+	/// * Any error that [`end`](MethodCall::end) can return.
+	#[cfg(feature = "alloc")]
+	pub fn end_grow(
+		self,
+		initial: usize,
+	) -> core::result::Result<Vec<u8>, MethodCallError<'invoker>> {
+		let mut buffer = vec![0; initial];
+		let len = match self.end(&mut buffer) {
+			InvokeEndResult::Done(result) => result?,
+			InvokeEndResult::BufferTooShort(call) => {
+				let (len, call) = match call.end_length() {
+					InvokeEndLengthResult::Done(result) => result?,
+					InvokeEndLengthResult::Pending(_) => return Err(MethodCallError::QueueEmpty),
+				};
+				buffer.resize(len, 0);
+				call.end(&mut buffer).expect_done()?
+			}
+			InvokeEndResult::Pending(_) => return Err(MethodCallError::QueueEmpty),
+		};
+		buffer.truncate(len);
+		Ok(buffer)
+	}
+
+	/// Returns the result of the method call as a CBOR-encoded data item in a newly allocated
+	/// buffer of exactly the right size.
+	///
+	/// The length of the result is queried first, then a buffer of that size is allocated and the
+	/// result is fetched into it. Should the result nevertheless not fit, the length is queried and
+	/// the fetch retried once more.
+	///
+	/// # Errors
+	/// * [`QueueEmpty`](MethodCallError::QueueEmpty) is returned if the method call is not
+	///   finished, in which case it is cancelled.
+	/// * [`BufferTooShort`](MethodCallError::BufferTooShort) is returned if the result still does
+	///   not fit after retrying.
+	/// * Any error that [`end`](MethodCall::end) can return.
+	#[cfg(feature = "alloc")]
+	pub fn end_exact(self) -> core::result::Result<Vec<u8>, MethodCallError<'invoker>> {
+		let mut call = self;
+		let mut buffer = Vec::new();
+		for _ in 0..2 {
+			let (len, c) = match call.end_length() {
+				InvokeEndLengthResult::Done(result) => result?,
+				InvokeEndLengthResult::Pending(_) => return Err(MethodCallError::QueueEmpty),
+			};
+			buffer.resize(len, 0);
+			match c.end(&mut buffer) {
+				InvokeEndResult::Done(result) => {
+					buffer.truncate(result?);
+					return Ok(buffer);
+				}
+				InvokeEndResult::BufferTooShort(c) => call = c,
+				InvokeEndResult::Pending(_) => return Err(MethodCallError::QueueEmpty),
+			}
+		}
+		Err(MethodCallError::BufferTooShort)
+	}
+
+	/// Cancels the method call.
+	///
+	/// This is equivalent to dropping the `MethodCall`, but makes the intent explicit. If the
+	/// method has not executed yet, it will not execute; if it has already executed, its result is
+	/// discarded.
+	pub fn cancel(self) {
+		drop(self);
+	}
 }
 
 impl Drop for MethodCall<'_> {
@@ -886,6 +1616,74 @@ impl Drop for MethodCall<'_> {
 	}
 }
 
+/// The kind of call a [`MethodCall`](MethodCall) was started as, and what it was started on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(not(feature = "debug-calls"), allow(dead_code))] // Only read when debug-calls records it.
+enum CallTarget {
+	/// A method on the component with the given address.
+	ComponentMethod(Address),
+
+	/// A call of the opaque value with the given raw descriptor.
+	Value(u32),
+
+	/// An indexed read of the opaque value with the given raw descriptor.
+	ValueIndexedRead(u32),
+
+	/// An indexed write of the opaque value with the given raw descriptor.
+	ValueIndexedWrite(u32),
+
+	/// A method on the opaque value with the given raw descriptor.
+	ValueMethod(u32),
+}
+
+/// A record of what a [`MethodCall`](MethodCall) was started on, kept when the `debug-calls`
+/// feature is enabled.
+#[cfg(feature = "debug-calls")]
+#[derive(Clone, Copy, Eq, PartialEq)]
+struct CallContext {
+	/// What was called.
+	target: CallTarget,
+
+	/// The method name, truncated to fit if necessary.
+	method: [u8; Self::METHOD_LEN],
+
+	/// The number of bytes of `method` that are used.
+	method_len: usize,
+}
+
+#[cfg(feature = "debug-calls")]
+impl CallContext {
+	/// The maximum number of bytes of method name to keep.
+	const METHOD_LEN: usize = 32;
+
+	/// Records a call.
+	fn new(target: CallTarget, method: &str) -> Self {
+		let mut method_len = method.len().min(Self::METHOD_LEN);
+		while !method.is_char_boundary(method_len) {
+			method_len -= 1;
+		}
+		let mut buffer = [0; Self::METHOD_LEN];
+		buffer[..method_len].copy_from_slice(&method.as_bytes()[..method_len]);
+		Self {
+			target,
+			method: buffer,
+			method_len,
+		}
+	}
+}
+
+#[cfg(feature = "debug-calls")]
+impl core::fmt::Debug for CallContext {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		// The bytes were copied from a str and truncated on a character boundary.
+		let method = core::str::from_utf8(&self.method[..self.method_len]).unwrap_or_default();
+		f.debug_struct("CallContext")
+			.field("target", &self.target)
+			.field("method", &method)
+			.finish()
+	}
+}
+
 /// An object that is able to retrieve detailed method call error information.
 ///
 /// Certain errors, when returned from a method call (and only from a method call!), are
@@ -912,7 +1710,7 @@ impl<'invoker> LastException<'invoker> {
 	pub fn message_length(&self) -> usize {
 		// SAFETY: last_exception_message permits null.
 		let result = unsafe { call_buffer_len(sys::last_exception_message) };
-		result.unwrap_or_else(|_| panic_or_trap!("unreachable"))
+		result.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e))
 	}
 
 	/// Returns the human-readable message for the error.
@@ -935,7 +1733,7 @@ impl<'invoker> LastException<'invoker> {
 		match result {
 			Ok(message) => Ok(message),
 			Err(Error::BufferTooShort) => Err(Error::BufferTooShort),
-			Err(_) => panic_or_trap!("unreachable"),
+			Err(e) => panic_or_trap!("unreachable: {}", e),
 		}
 	}
 
@@ -953,7 +1751,7 @@ impl<'invoker> LastException<'invoker> {
 	pub fn is_type(&self, class: &str) -> bool {
 		// SAFETY: is_type permits a string pointer/length pair.
 		let result = unsafe { call_string(sys::last_exception_is_type, Some(class)) };
-		result.unwrap_or_else(|_| panic_or_trap!("unreachable")) != 0
+		result.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e)) != 0
 	}
 }
 
@@ -1070,14 +1868,17 @@ impl TryFrom<Error> for MethodCallError<'static> {
 			Error::BadDescriptor => Ok(Self::BadDescriptor),
 			Error::TooManyDescriptors => Ok(Self::TooManyDescriptors),
 			Error::Unknown => Ok(Self::Unknown),
-			Error::BadParameters | Error::Other => Err(()),
+			Error::BadParameters | Error::Other | Error::DuplicateDescriptor => Err(()),
 		}
 	}
 }
 
-#[cfg(feature = "std")]
+#[cfg(all(feature = "std", not(feature = "core-error")))]
 impl std::error::Error for MethodCallError<'_> {}
 
+#[cfg(feature = "core-error")]
+impl core::error::Error for MethodCallError<'_> {}
+
 /// The result of a call to [`end_length`](MethodCall::end_length).
 ///
 /// The `'invoker` lifetime parameter is the lifetime of the method invoker that is performing the
@@ -1138,3 +1939,1300 @@ impl<'invoker> InvokeEndResult<'invoker> {
 		}
 	}
 }
+
+/// An iterator over the descriptors in a CBOR-encoded array returned from a method call.
+///
+/// A value of this type is created by [`result_descriptors`](result_descriptors). Each element of
+/// the array is expected to be a descriptor (an integer with the Identifier tag). Well-formed
+/// elements are yielded as [`Owned`](Owned) descriptors. Malformed elements are skipped and yield
+/// [`CborDecode`](Error::CborDecode), after which iteration continues with the next element; if
+/// a malformed element cannot even be skipped, iteration stops.
+///
+/// A well-formed method call result never contains the same descriptor twice. If a descriptor
+/// does appear more than once, only its first occurrence is yielded as an `Owned`; each later
+/// occurrence yields [`DuplicateDescriptor`](Error::DuplicateDescriptor) instead, so that two
+/// `Owned` values never refer to the same descriptor.
+#[derive(Debug)]
+pub struct ResultDescriptors<'buffer> {
+	/// The descriptors already yielded.
+	seen: SeenDescriptors<'buffer>,
+
+	/// The decoder positioned at the next element.
+	decoder: Decoder<'buffer>,
+
+	/// The number of elements not yet yielded.
+	remaining: u64,
+}
+
+impl Iterator for ResultDescriptors<'_> {
+	type Item = Result<Owned>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.remaining == 0 {
+			return None;
+		}
+		self.remaining -= 1;
+		let element = self.decoder.clone();
+		let position = self.decoder.position();
+		if let Ok(descriptor) = self.decoder.decode::<Decoded>() {
+			if !self.seen.insert(&descriptor, &element) {
+				return Some(Err(Error::DuplicateDescriptor));
+			}
+			// SAFETY: The caller of result_descriptors promised that the buffer holds a method call
+			// result that has not been decoded before, so each descriptor is valid, and
+			// SeenDescriptors::insert verified that this is the only reference to it.
+			Some(Ok(unsafe { descriptor.into_owned() }))
+		} else {
+			self.decoder.set_position(position);
+			if self.decoder.skip().is_err() {
+				self.remaining = 0;
+			}
+			Some(Err(Error::CborDecode))
+		}
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = usize::try_from(self.remaining).unwrap_or(usize::MAX);
+		(0, Some(remaining))
+	}
+}
+
+/// The number of descriptors that a [`SeenDescriptors`](SeenDescriptors) records.
+const SEEN_CAPACITY: usize = 16;
+
+/// The descriptors already taken from the elements of an array, used to reject duplicates.
+///
+/// The first [`SEEN_CAPACITY`](SEEN_CAPACITY) descriptors are recorded in a sorted array, so
+/// checking one is a binary search rather than a rescan of the elements before it. Once the array
+/// is full, later descriptors are checked by scanning the elements from the first unrecorded one
+/// onward, which is only needed for results holding an unusually large number of descriptors.
+#[derive(Clone, Debug)]
+struct SeenDescriptors<'buffer> {
+	/// The raw values of the recorded descriptors, sorted; only the first `len` are meaningful.
+	raw: [u32; SEEN_CAPACITY],
+
+	/// The number of recorded descriptors.
+	len: usize,
+
+	/// A decoder positioned at the element holding the first descriptor that was not recorded.
+	overflow: Option<Decoder<'buffer>>,
+}
+
+impl<'buffer> SeenDescriptors<'buffer> {
+	/// Creates an empty set.
+	fn new() -> Self {
+		Self {
+			raw: [0; SEEN_CAPACITY],
+			len: 0,
+			overflow: None,
+		}
+	}
+
+	/// Adds a descriptor to the set.
+	///
+	/// The `element` parameter is a decoder positioned at the array element holding `descriptor`.
+	///
+	/// Returns `true` if the descriptor was added, or `false` if it was already taken.
+	fn insert(&mut self, descriptor: &Decoded, element: &Decoder<'buffer>) -> bool {
+		let raw = descriptor.as_raw();
+		let Err(index) = self.raw[..self.len].binary_search(&raw) else {
+			return false;
+		};
+		if let Some(ref overflow) = self.overflow {
+			!descriptor_before(overflow, descriptor, element.position())
+		} else if self.len == SEEN_CAPACITY {
+			self.overflow = Some(element.clone());
+			true
+		} else {
+			self.raw.copy_within(index..self.len, index + 1);
+			self.raw[index] = raw;
+			self.len += 1;
+			true
+		}
+	}
+}
+
+/// Checks whether a descriptor appears in any element of an array before a given position.
+///
+/// The `from` parameter is a decoder positioned at the first element to check.
+fn descriptor_before(from: &Decoder<'_>, descriptor: &Decoded, position: usize) -> bool {
+	let mut decoder = from.clone();
+	while decoder.position() < position {
+		let element_position = decoder.position();
+		match decoder.decode::<Decoded>() {
+			Ok(ref other) if other == descriptor => return true,
+			Ok(_) => (),
+			Err(_) => {
+				decoder.set_position(element_position);
+				if decoder.skip().is_err() {
+					return false;
+				}
+			}
+		}
+	}
+	false
+}
+
+/// Iterates over the descriptors in a CBOR-encoded array returned from a method call.
+///
+/// The `buffer` parameter is the CBOR-encoded array, which must be the complete result of a method
+/// call as written by [`MethodCall::end`](MethodCall::end), or an array nested within such a
+/// result.
+///
+/// The descriptors are decoded lazily, one per call to `next`, allowing the caller to process
+/// each one before moving on to the next.
+///
+/// # Errors
+/// * [`CborDecode`](Error::CborDecode) is returned if `buffer` does not start with a
+///   definite-length CBOR array.
+///
+/// # Safety
+/// The caller must ensure that the descriptors in `buffer` are valid and that no other object
+/// refers to them. Generally, this is accomplished by obtaining `buffer` from a method call and
+/// decoding it exactly once, because OC-Wasm guarantees that any opaque value returned from a
+/// method call is represented by a fresh descriptor. See
+/// [`Decoded::into_owned`](Decoded::into_owned) for details.
+pub unsafe fn result_descriptors(buffer: &[u8]) -> Result<ResultDescriptors<'_>> {
+	let mut decoder = Decoder::new(buffer);
+	let remaining = decoder.array().ok().flatten().ok_or(Error::CborDecode)?;
+	Ok(ResultDescriptors {
+		seen: SeenDescriptors::new(),
+		decoder,
+		remaining,
+	})
+}
+
+/// A reader for the return values in a CBOR-encoded method call result.
+///
+/// A method call result is an array of return values. A value of this type decodes the array
+/// header once, then reads the return values one at a time, in order, each typed accessor
+/// advancing to the next value.
+#[derive(Clone, Debug)]
+pub struct ResultReader<'buffer> {
+	/// The descriptors already returned.
+	seen: SeenDescriptors<'buffer>,
+
+	/// The decoder positioned at the next return value.
+	decoder: Decoder<'buffer>,
+
+	/// The number of return values not yet read.
+	remaining: u64,
+}
+
+impl<'buffer> ResultReader<'buffer> {
+	/// Creates a reader over a method call result.
+	///
+	/// The `buffer` parameter is the CBOR-encoded result, as written by
+	/// [`MethodCall::end`](MethodCall::end).
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if `buffer` does not start with a
+	///   definite-length CBOR array.
+	pub fn new(buffer: &'buffer [u8]) -> Result<Self> {
+		let mut decoder = Decoder::new(buffer);
+		let remaining = decoder.array().ok().flatten().ok_or(Error::CborDecode)?;
+		Ok(Self {
+			seen: SeenDescriptors::new(),
+			decoder,
+			remaining,
+		})
+	}
+
+	/// Returns the number of return values that have not yet been read.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn remaining(&self) -> usize {
+		usize::try_from(self.remaining).unwrap_or(usize::MAX)
+	}
+
+	/// Reads the next return value as an unsigned integer.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if there are no more return values or the
+	///   next value is not an integer representable as a `u32`.
+	///
+	/// On error, the reader does not advance.
+	pub fn next_u32(&mut self) -> Result<u32> {
+		self.read(Decoder::u32)
+	}
+
+	/// Reads the next return value as a signed integer.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if there are no more return values or the
+	///   next value is not an integer representable as an `i64`.
+	///
+	/// On error, the reader does not advance.
+	pub fn next_i64(&mut self) -> Result<i64> {
+		self.read(Decoder::i64)
+	}
+
+	/// Reads the next return value as a floating-point number.
+	///
+	/// Integers are accepted as well as floating-point numbers, because numbers with no
+	/// fractional part are encoded as integers.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if there are no more return values or the
+	///   next value is not a number.
+	///
+	/// On error, the reader does not advance.
+	pub fn next_f64(&mut self) -> Result<f64> {
+		self.read(|d| match d.datatype()? {
+			Type::F16 | Type::F32 | Type::F64 => d.f64(),
+			// Precision loss is inherent in representing a large integer as a float.
+			#[allow(clippy::cast_precision_loss)]
+			_ => d.i64().map(|i| i as f64),
+		})
+	}
+
+	/// Reads the next return value as a boolean.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if there are no more return values or the
+	///   next value is not a boolean.
+	///
+	/// On error, the reader does not advance.
+	pub fn next_bool(&mut self) -> Result<bool> {
+		self.read(Decoder::bool)
+	}
+
+	/// Reads the next return value as a string.
+	///
+	/// The returned string slice refers to the result buffer.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if there are no more return values or the
+	///   next value is not a definite-length UTF-8 string.
+	///
+	/// On error, the reader does not advance.
+	pub fn next_str(&mut self) -> Result<&'buffer str> {
+		self.read(Decoder::str)
+	}
+
+	/// Reads the next return value as an opaque value descriptor.
+	///
+	/// A well-formed method call result never contains the same descriptor twice. If the next
+	/// return value is a descriptor that this reader already returned for an earlier return value,
+	/// it is skipped and [`DuplicateDescriptor`](Error::DuplicateDescriptor) is returned instead,
+	/// so that two [`Owned`](Owned) values never refer to the same descriptor.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if there are no more return values or the
+	///   next value is not a descriptor (an integer with the Identifier tag). The reader does not
+	///   advance.
+	/// * [`DuplicateDescriptor`](Error::DuplicateDescriptor) is returned if the descriptor was
+	///   already returned for an earlier return value. The reader advances past it.
+	///
+	/// # Safety
+	/// The caller must ensure that the reader was created over the genuine result of a method
+	/// call, as written by [`MethodCall::end`](MethodCall::end), and that the descriptors in that
+	/// result have not already been decoded into [`Owned`](Owned) values, whether by this reader,
+	/// another reader over the same buffer, or any other means. This is what makes the conversion
+	/// sound: OC-Wasm guarantees that any opaque value returned from a method call is represented
+	/// by a fresh descriptor, so a descriptor decoded exactly once from a real result is valid and
+	/// has no other owner. See [`Decoded::into_owned`](Decoded::into_owned) for details.
+	pub unsafe fn next_descriptor(&mut self) -> Result<Owned> {
+		let element = self.decoder.clone();
+		let descriptor = self.read(Decoder::decode::<Decoded>)?;
+		if !self.seen.insert(&descriptor, &element) {
+			return Err(Error::DuplicateDescriptor);
+		}
+		// SAFETY: The caller promised that the buffer is a genuine method call result whose
+		// descriptors have not been decoded before, and SeenDescriptors::insert verified that this
+		// is the only reference to this one within the result.
+		Ok(descriptor.into_owned())
+	}
+
+	/// Skips over the next return value, whatever its type.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if there are no more return values or the
+	///   next value is malformed.
+	///
+	/// On error, the reader does not advance.
+	pub fn skip(&mut self) -> Result<()> {
+		self.read(Decoder::skip)
+	}
+
+	/// Checks whether the result follows the convention for reporting a failure.
+	///
+	/// Many methods report failure not by throwing an exception but by returning `nil` or `false`
+	/// followed by a string describing the problem. If the unread return values have that form,
+	/// they are consumed and the message is returned as a [`MethodError`](MethodError).
+	/// Otherwise, the reader is left where it was, ready to read the real return values.
+	///
+	/// # Errors
+	/// * [`MethodError`](MethodError) is returned if the next two return values are `nil` or
+	///   `false` followed by a string.
+	pub fn check_ok(&mut self) -> core::result::Result<(), MethodError<'buffer>> {
+		if self.remaining >= 2 {
+			let mut decoder = self.decoder.clone();
+			let failed = match decoder.datatype() {
+				Ok(Type::Null | Type::Undefined) => decoder.skip().is_ok(),
+				Ok(Type::Bool) => matches!(decoder.bool(), Ok(false)),
+				_ => false,
+			};
+			if failed {
+				if let Ok(message) = decoder.str() {
+					self.decoder = decoder;
+					self.remaining -= 2;
+					return Err(MethodError(message));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads the next return value using a decoding function.
+	///
+	/// If there are no more return values or `f` fails, [`CborDecode`](Error::CborDecode) is
+	/// returned and the decoder is restored to its previous position.
+	fn read<T>(
+		&mut self,
+		f: impl FnOnce(&mut Decoder<'buffer>) -> core::result::Result<T, minicbor::decode::Error>,
+	) -> Result<T> {
+		if self.remaining == 0 {
+			return Err(Error::CborDecode);
+		}
+		let position = self.decoder.position();
+		if let Ok(value) = f(&mut self.decoder) {
+			self.remaining -= 1;
+			Ok(value)
+		} else {
+			self.decoder.set_position(position);
+			Err(Error::CborDecode)
+		}
+	}
+}
+
+/// A failure reported by a method through its return values.
+///
+/// The `'buffer` lifetime parameter is the lifetime of the buffer holding the method call result,
+/// which contains the message.
+///
+/// A value of this type is returned by [`ResultReader::check_ok`](ResultReader::check_ok).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MethodError<'buffer>(&'buffer str);
+
+impl<'buffer> MethodError<'buffer> {
+	/// Returns the message describing the failure.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn message(&self) -> &'buffer str {
+		self.0
+	}
+}
+
+impl Display for MethodError<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		f.write_str(self.0)
+	}
+}
+
+#[cfg(all(feature = "std", not(feature = "core-error")))]
+impl std::error::Error for MethodError<'_> {}
+
+#[cfg(feature = "core-error")]
+impl core::error::Error for MethodError<'_> {}
+
+/// A component, identified by its address.
+///
+/// This is a thin wrapper around an [`Address`](Address) whose methods forward to the free
+/// functions and singleton methods in this module that take an address, so that code which works
+/// with the same component repeatedly does not need to pass the address to every call.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Component(Address);
+
+impl Component {
+	/// Creates a component wrapper for the given address.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn new(address: Address) -> Self {
+		Self(address)
+	}
+
+	/// Returns the address of the component.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn address(&self) -> &Address {
+		&self.0
+	}
+
+	/// Returns the type of the component.
+	///
+	/// This is equivalent to [`component_type`](component_type).
+	///
+	/// # Errors
+	/// Any error that [`component_type`](component_type) can return.
+	pub fn type_name<'buf>(&self, buffer: &'buf mut [u8]) -> Result<&'buf mut str> {
+		component_type(&self.0, buffer)
+	}
+
+	/// Returns the documentation for a method on the component.
+	///
+	/// This is equivalent to [`documentation_component`](documentation_component).
+	///
+	/// # Errors
+	/// Any error that [`documentation_component`](documentation_component) can return.
+	pub fn documentation<'buf>(
+		&self,
+		method: &str,
+		buffer: &'buf mut [u8],
+	) -> Result<&'buf mut str> {
+		documentation_component(&self.0, method, buffer)
+	}
+
+	/// Begins iteration over the methods available on the component.
+	///
+	/// This is equivalent to [`MethodLister::start_component`](MethodLister::start_component).
+	///
+	/// # Errors
+	/// Any error that [`MethodLister::start_component`](MethodLister::start_component) can return.
+	pub fn methods<'lister>(
+		&self,
+		lister: &'lister mut MethodLister,
+	) -> Result<MethodListing<'lister>> {
+		lister.start_component(&self.0)
+	}
+
+	/// Starts invoking a method on the component.
+	///
+	/// This is equivalent to [`Invoker::component_method`](Invoker::component_method).
+	///
+	/// # Errors
+	/// Any error that [`Invoker::component_method`](Invoker::component_method) can return.
+	pub fn invoke<'invoker>(
+		&self,
+		invoker: &'invoker mut Invoker,
+		method: &str,
+		params: Option<&[u8]>,
+	) -> Result<(InvokeResult, MethodCall<'invoker>)> {
+		invoker.component_method(&self.0, method, params)
+	}
+}
+
+impl From<Address> for Component {
+	fn from(address: Address) -> Self {
+		Self(address)
+	}
+}
+
+/// Checks that a string is plausible as a component type or method name.
+///
+/// # Errors
+/// * [`BadParameters`](Error::BadParameters) is returned if `name` is empty or contains a control
+///   character (including NUL).
+fn validate_name(name: &str) -> Result<&str> {
+	if name.is_empty() || name.chars().any(char::is_control) {
+		Err(Error::BadParameters)
+	} else {
+		Ok(name)
+	}
+}
+
+/// A component type name that has been checked for plausibility.
+///
+/// Components never have an empty type name or one containing control characters, so a value
+/// of this type rules those out at construction time, rather than letting a mistyped name surface
+/// later as [`NoSuchComponent`](Error::NoSuchComponent). A value of this type dereferences to
+/// `str`, so it can be passed to any function that takes a component type as a `&str`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ComponentType<'a>(&'a str);
+
+impl<'a> ComponentType<'a> {
+	/// Checks and wraps a component type name.
+	///
+	/// # Errors
+	/// * [`BadParameters`](Error::BadParameters) is returned if `name` is empty or contains a
+	///   control character (including NUL).
+	pub fn new(name: &'a str) -> Result<Self> {
+		Ok(Self(validate_name(name)?))
+	}
+
+	/// Returns the type name as a string slice.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn as_str(&self) -> &'a str {
+		self.0
+	}
+}
+
+impl core::ops::Deref for ComponentType<'_> {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		self.0
+	}
+}
+
+impl Display for ComponentType<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		f.write_str(self.0)
+	}
+}
+
+impl<'a> From<ComponentType<'a>> for &'a str {
+	fn from(value: ComponentType<'a>) -> Self {
+		value.0
+	}
+}
+
+impl<'a> TryFrom<&'a str> for ComponentType<'a> {
+	type Error = Error;
+
+	fn try_from(value: &'a str) -> Result<Self> {
+		Self::new(value)
+	}
+}
+
+/// A method name that has been checked for plausibility.
+///
+/// Components never have an empty method name or one containing control characters, so a
+/// value of this type rules those out at construction time, rather than letting a mistyped name
+/// surface later as [`NoSuchMethod`](Error::NoSuchMethod). A value of this type dereferences to
+/// `str`, so it can be passed to any function that takes a method name as a `&str`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MethodName<'a>(&'a str);
+
+impl<'a> MethodName<'a> {
+	/// Checks and wraps a method name.
+	///
+	/// # Errors
+	/// * [`BadParameters`](Error::BadParameters) is returned if `name` is empty or contains a
+	///   control character (including NUL).
+	pub fn new(name: &'a str) -> Result<Self> {
+		Ok(Self(validate_name(name)?))
+	}
+
+	/// Returns the method name as a string slice.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn as_str(&self) -> &'a str {
+		self.0
+	}
+}
+
+impl core::ops::Deref for MethodName<'_> {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		self.0
+	}
+}
+
+impl Display for MethodName<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		f.write_str(self.0)
+	}
+}
+
+impl<'a> From<MethodName<'a>> for &'a str {
+	fn from(value: MethodName<'a>) -> Self {
+		value.0
+	}
+}
+
+impl<'a> TryFrom<&'a str> for MethodName<'a> {
+	type Error = Error;
+
+	fn try_from(value: &'a str) -> Result<Self> {
+		Self::new(value)
+	}
+}
+
+/// An abstraction over the component subsystem.
+///
+/// This trait exposes the most common component operations—listing components, examining their
+/// types and methods, and invoking methods—through a single interface. Application logic written
+/// against `&mut impl Components` can be run on a real computer using
+/// [`Environment`](Environment), or off-target against a fake implementation.
+pub trait Components {
+	/// Lists the components attached to the computer.
+	///
+	/// The `component_type` parameter, if present, restricts the listing to only return components
+	/// of the specified type. The `f` parameter is called once with the address of each component.
+	fn list(&mut self, component_type: Option<&str>, f: &mut dyn FnMut(&Address));
+
+	/// Returns the type of a component.
+	///
+	/// The `address` parameter identifies the component by its UUID. The `buffer` parameter
+	/// identifies where to store the component type.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the component type.
+	/// * [`NoSuchComponent`](Error::NoSuchComponent) is returned if the component does not exist
+	///   or is inaccessible.
+	fn component_type<'buf>(
+		&mut self,
+		address: &Address,
+		buffer: &'buf mut [u8],
+	) -> Result<&'buf mut str>;
+
+	/// Lists the methods available on a component.
+	///
+	/// The `address` parameter identifies the component by its UUID. The `buffer` parameter is
+	/// scratch space used to hold each method name. The `f` parameter is called once with the name
+	/// and attributes of each method.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold a method name.
+	/// * [`NoSuchComponent`](Error::NoSuchComponent) is returned if the component does not exist
+	///   or is inaccessible.
+	fn method_names(
+		&mut self,
+		address: &Address,
+		buffer: &mut [u8],
+		f: &mut dyn FnMut(&str, MethodAttributes),
+	) -> Result<()>;
+
+	/// Invokes a method on a component and fetches its result.
+	///
+	/// The `address` parameter identifies the component by its UUID. The `method` parameter
+	/// identifies the method by its name. The `params` parameter, if present, contains a
+	/// CBOR-encoded array of parameters to pass to the method. The `buffer` parameter identifies
+	/// where to store the CBOR-encoded result.
+	///
+	/// On success, the number of bytes written to `buffer` is returned.
+	///
+	/// # Errors
+	/// * [`QueueEmpty`](Error::QueueEmpty) is returned if the method call does not complete
+	///   immediately, in which case it is cancelled.
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the result, in which case the result is discarded.
+	/// * Any error that [`Invoker::component_method`](Invoker::component_method) or
+	///   [`MethodCall::end`](MethodCall::end) can return.
+	fn invoke(
+		&mut self,
+		address: &Address,
+		method: &str,
+		params: Option<&[u8]>,
+		buffer: &mut [u8],
+	) -> Result<usize>;
+}
+
+/// The set of all listers, of which only one can be in use at a time.
+///
+/// Component listings, method listings, and access control list iterations are each tracked by
+/// OC-Wasm separately, but whether interleaving them is safe is not guaranteed. A value of this
+/// type holds all three listers and only ever lends out one of them at a time, so the borrow
+/// checker ensures that at most one listing operation of any kind is in progress.
+///
+/// A value of this type is obtained with [`take`](Listers::take), or built with
+/// [`new`](Listers::new) from individual listers that were taken separately.
+///
+/// Starting one kind of listing while another is still in use does not compile:
+/// ```compile_fail,E0499
+/// use oc_wasm_safe::component::Listers;
+///
+/// let mut listers = Listers::take().unwrap();
+/// let mut listing = listers.components().start(None);
+/// let acl = listers.acl().start();
+/// let _ = listing.next();
+/// ```
+pub struct Listers {
+	/// The component lister.
+	lister: Lister,
+
+	/// The method lister.
+	method_lister: MethodLister,
+
+	/// The access control list lister.
+	acl_lister: AclLister,
+}
+
+impl Listers {
+	/// Returns the listers.
+	///
+	/// This takes the [`Lister`](Lister), [`MethodLister`](MethodLister), and
+	/// [`AclLister`](AclLister) together. If any of them has already been taken, `None` is
+	/// returned and none of them are taken.
+	#[must_use = "The listers can only be taken once. They need to be saved. Discarding them means it is impossible to ever list anything."]
+	pub fn take() -> Option<Self> {
+		let lister = Lister::take();
+		let method_lister = MethodLister::take();
+		let acl_lister = AclLister::take();
+		match (lister, method_lister, acl_lister) {
+			(Some(lister), Some(method_lister), Some(acl_lister)) => {
+				Some(Self::new(lister, method_lister, acl_lister))
+			}
+			(lister, method_lister, acl_lister) => {
+				if let Some(lister) = lister {
+					lister.restore();
+				}
+				if let Some(method_lister) = method_lister {
+					method_lister.restore();
+				}
+				if let Some(acl_lister) = acl_lister {
+					acl_lister.restore();
+				}
+				None
+			}
+		}
+	}
+
+	/// Creates a new `Listers` from the individual listers.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn new(lister: Lister, method_lister: MethodLister, acl_lister: AclLister) -> Self {
+		Self {
+			lister,
+			method_lister,
+			acl_lister,
+		}
+	}
+
+	/// Returns the component lister.
+	pub fn components(&mut self) -> &mut Lister {
+		&mut self.lister
+	}
+
+	/// Returns the method lister.
+	pub fn methods(&mut self) -> &mut MethodLister {
+		&mut self.method_lister
+	}
+
+	/// Returns the access control list lister.
+	pub fn acl(&mut self) -> &mut AclLister {
+		&mut self.acl_lister
+	}
+
+	/// Returns the individual listers.
+	#[must_use = "The listers can only be taken once. Discarding them means it is impossible to ever use them again."]
+	pub fn into_parts(self) -> (Lister, MethodLister, AclLister) {
+		(self.lister, self.method_lister, self.acl_lister)
+	}
+}
+
+/// All of the singleton capability tokens needed to interact with components.
+///
+/// A value of this type holds the [`Invoker`](Invoker) and the [`Listers`](Listers). An
+/// application can obtain it once at startup with [`take`](Environment::take), or build it from
+/// tokens it already holds with [`new`](Environment::new), and pass it down to whichever parts of
+/// the program need it, rather than threading through each token separately.
+/// The invoker and the listers are independent, so [`split`](Environment::split) can be used to
+/// borrow both at once.
+pub struct Environment {
+	/// The method invoker.
+	invoker: Invoker,
+
+	/// The listers.
+	listers: Listers,
+}
+
+impl Environment {
+	/// Returns the environment.
+	///
+	/// This takes the [`Invoker`](Invoker) and the [`Listers`](Listers) together. If any of the
+	/// tokens has already been taken, `None` is returned and none of them are taken.
+	#[must_use = "The environment can only be taken once. It needs to be saved. Discarding it means it is impossible to ever use its tokens."]
+	pub fn take() -> Option<Self> {
+		let invoker = Invoker::take()?;
+		if let Some(listers) = Listers::take() {
+			Some(Self::new(invoker, listers))
+		} else {
+			invoker.restore();
+			None
+		}
+	}
+
+	/// Creates a new `Environment` from the invoker and listers.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn new(invoker: Invoker, listers: Listers) -> Self {
+		Self { invoker, listers }
+	}
+
+	/// Returns the method invoker.
+	pub fn invoker(&mut self) -> &mut Invoker {
+		&mut self.invoker
+	}
+
+	/// Returns the listers.
+	pub fn listers(&mut self) -> &mut Listers {
+		&mut self.listers
+	}
+
+	/// Returns the method invoker and the listers at the same time.
+	pub fn split(&mut self) -> (&mut Invoker, &mut Listers) {
+		(&mut self.invoker, &mut self.listers)
+	}
+
+	/// Returns the invoker and listers.
+	#[must_use = "The environment can only be taken once. Discarding its parts means it is impossible to ever use them again."]
+	pub fn into_parts(self) -> (Invoker, Listers) {
+		(self.invoker, self.listers)
+	}
+}
+
+impl Components for Environment {
+	fn list(&mut self, component_type: Option<&str>, f: &mut dyn FnMut(&Address)) {
+		let mut listing = self.listers.components().start(component_type);
+		while let Some(entry) = listing.next() {
+			f(entry.address());
+		}
+	}
+
+	fn component_type<'buf>(
+		&mut self,
+		address: &Address,
+		buffer: &'buf mut [u8],
+	) -> Result<&'buf mut str> {
+		component_type(address, buffer)
+	}
+
+	fn method_names(
+		&mut self,
+		address: &Address,
+		buffer: &mut [u8],
+		f: &mut dyn FnMut(&str, MethodAttributes),
+	) -> Result<()> {
+		let mut listing = self.listers.methods().start_component(address)?;
+		while let Some((name, attributes)) = listing.next(buffer)? {
+			f(name, attributes);
+		}
+		Ok(())
+	}
+
+	fn invoke(
+		&mut self,
+		address: &Address,
+		method: &str,
+		params: Option<&[u8]>,
+		buffer: &mut [u8],
+	) -> Result<usize> {
+		let (_, call) = self.invoker.component_method(address, method, params)?;
+		Ok(call.end(buffer).expect_done()?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn result_reader_mixed_values() {
+		// ["ok", 42, true]
+		let buffer = [0x83, 0x62, b'o', b'k', 0x18, 42, 0xF5];
+		let mut reader = ResultReader::new(&buffer).unwrap();
+		assert_eq!(reader.remaining(), 3);
+		assert_eq!(reader.check_ok(), Ok(()));
+		assert_eq!(reader.next_bool(), Err(Error::CborDecode));
+		assert_eq!(reader.next_str(), Ok("ok"));
+		assert_eq!(reader.next_str(), Err(Error::CborDecode));
+		assert_eq!(reader.next_u32(), Ok(42));
+		assert_eq!(reader.next_bool(), Ok(true));
+		assert_eq!(reader.remaining(), 0);
+		assert_eq!(reader.next_bool(), Err(Error::CborDecode));
+	}
+
+	#[test]
+	fn result_reader_check_ok_failure() {
+		// [null, "msg"]
+		let buffer = [0x82, 0xF6, 0x63, b'm', b's', b'g'];
+		let mut reader = ResultReader::new(&buffer).unwrap();
+		assert_eq!(reader.check_ok().unwrap_err().message(), "msg");
+		assert_eq!(reader.remaining(), 0);
+	}
+
+	#[test]
+	fn result_reader_check_ok_success() {
+		// [false, 7]
+		let buffer = [0x82, 0xF4, 0x07];
+		let mut reader = ResultReader::new(&buffer).unwrap();
+		assert_eq!(reader.check_ok(), Ok(()));
+		assert_eq!(reader.remaining(), 2);
+		assert_eq!(reader.next_bool(), Ok(false));
+		assert_eq!(reader.next_i64(), Ok(7));
+	}
+
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	mod mock_backed {
+		use super::*;
+		use crate::mock;
+
+		/// Opens a descriptor in the mock and returns it as a single CBOR byte.
+		fn open_descriptor() -> u8 {
+			u8::try_from(mock::new_descriptor()).unwrap()
+		}
+
+		#[test]
+		fn try_next_reports_unexpected_code() {
+			mock::reset();
+			let address = Address::from_bytes([0x36; 16]);
+			mock::register_component(address, "example", &[]);
+			let mut lister = Lister::take().unwrap();
+			let mut listing = lister.start(None);
+			mock::fail_next("component::list_next", Error::UNKNOWN_CODE);
+			assert_eq!(listing.try_next().err(), Some(Error::Unknown));
+			let entry = listing.try_next().unwrap().unwrap();
+			assert_eq!(*entry.address(), address);
+			mock::fail_next("component::list_type", -12);
+			assert_eq!(entry.try_type_name_len(), Err(Error::Other));
+			assert_eq!(entry.try_type_name_len().unwrap().get(), 7);
+		}
+
+		#[test]
+		fn try_next_len_reports_unexpected_code() {
+			mock::reset();
+			let address = Address::from_bytes([0x37; 16]);
+			let attributes = MethodAttributes {
+				direct: true,
+				getter: false,
+				setter: false,
+			};
+			mock::register_component(address, "example", &[("m", attributes, &[0x80])]);
+			let mut lister = MethodLister::take().unwrap();
+			let listing = lister.start_component(&address).unwrap();
+			mock::fail_next("component::methods_next", -5);
+			assert_eq!(listing.try_next_len(), Err(Error::NoSuchComponent));
+			assert_eq!(listing.try_next_len().unwrap().unwrap().get(), 1);
+		}
+
+		/// Registers a component with a direct method `small` returning `[1]`, a direct method
+		/// `large` returning a 32-byte string, and an indirect method `slow` returning `[1]`.
+		fn register_result_component(address: Address) {
+			let direct = MethodAttributes {
+				direct: true,
+				getter: false,
+				setter: false,
+			};
+			let indirect = MethodAttributes {
+				direct: false,
+				..direct
+			};
+			let mut large = vec![0x81, 0x78, 32];
+			large.extend_from_slice(&[b'x'; 32]);
+			mock::register_component(address, "example", &[]);
+			mock::set_method(&address, "small", direct, |_| Ok(vec![0x81, 0x01]));
+			mock::set_method(&address, "large", direct, move |_| Ok(large.clone()));
+			mock::set_method(&address, "slow", indirect, |_| Ok(vec![0x81, 0x01]));
+		}
+
+		#[test]
+		fn end_grow_fits() {
+			mock::reset();
+			let address = Address::from_bytes([0x38; 16]);
+			register_result_component(address);
+			let mut invoker = Invoker::take().unwrap();
+			let (_, call) = invoker.component_method(&address, "small", None).unwrap();
+			assert_eq!(call.end_grow(16).unwrap(), [0x81, 0x01]);
+			assert_eq!(mock::syscall_count("component::invoke_end"), 1);
+		}
+
+		#[test]
+		fn end_grow_grows() {
+			mock::reset();
+			let address = Address::from_bytes([0x39; 16]);
+			register_result_component(address);
+			let mut invoker = Invoker::take().unwrap();
+			let (_, call) = invoker.component_method(&address, "large", None).unwrap();
+			assert_eq!(call.end_grow(4).unwrap().len(), 35);
+			assert_eq!(mock::syscall_count("component::invoke_end"), 3);
+		}
+
+		#[test]
+		fn end_grow_pending() {
+			mock::reset();
+			let address = Address::from_bytes([0x3A; 16]);
+			register_result_component(address);
+			let mut invoker = Invoker::take().unwrap();
+			let (_, call) = invoker.component_method(&address, "slow", None).unwrap();
+			assert!(matches!(
+				call.end_grow(16),
+				Err(MethodCallError::QueueEmpty)
+			));
+		}
+
+		#[test]
+		fn end_exact_fetches() {
+			mock::reset();
+			let address = Address::from_bytes([0x3B; 16]);
+			register_result_component(address);
+			let mut invoker = Invoker::take().unwrap();
+			let (_, call) = invoker.component_method(&address, "large", None).unwrap();
+			let result = call.end_exact().unwrap();
+			assert_eq!(result.len(), 35);
+			assert_eq!(mock::syscall_count("component::invoke_end"), 2);
+		}
+
+		#[test]
+		fn end_exact_pending() {
+			mock::reset();
+			let address = Address::from_bytes([0x3C; 16]);
+			register_result_component(address);
+			let mut invoker = Invoker::take().unwrap();
+			let (_, call) = invoker.component_method(&address, "slow", None).unwrap();
+			assert!(matches!(call.end_exact(), Err(MethodCallError::QueueEmpty)));
+		}
+
+		#[test]
+		fn environment_split() {
+			mock::reset();
+			let address = Address::from_bytes([0x3D; 16]);
+			register_result_component(address);
+			let mut environment = Environment::take().unwrap();
+			let (invoker, listers) = environment.split();
+			let mut listing = listers.components().start(None);
+			let entry = listing.next().unwrap();
+			let (_, call) = invoker
+				.component_method(entry.address(), "small", None)
+				.unwrap();
+			let mut buffer = [0_u8; 2];
+			assert_eq!(call.end(&mut buffer).expect_done(), Ok(2));
+			assert!(listing.next().is_none());
+		}
+
+		#[test]
+		fn listing_restart() {
+			mock::reset();
+			let first = Address::from_bytes([0x3E; 16]);
+			let second = Address::from_bytes([0x3F; 16]);
+			mock::register_component(first, "example", &[]);
+			mock::register_component(second, "other", &[]);
+			let mut lister = Lister::take().unwrap();
+			let mut listing = lister.start(Some("example"));
+			assert_eq!(*listing.next().unwrap().address(), first);
+			assert!(listing.next().is_none());
+			listing.restart(Some("example"));
+			assert_eq!(*listing.next().unwrap().address(), first);
+			assert!(listing.next().is_none());
+			listing.restart(None);
+			assert_eq!(*listing.next().unwrap().address(), first);
+			assert_eq!(*listing.next().unwrap().address(), second);
+			assert!(listing.next().is_none());
+		}
+
+		#[test]
+		fn call_component_decodes_owned_values() {
+			mock::reset();
+			let address = Address::from_bytes([0x40; 16]);
+			let attributes = MethodAttributes {
+				direct: true,
+				getter: false,
+				setter: false,
+			};
+			mock::register_component(
+				address,
+				"example",
+				&[
+					("number", attributes, &[0x82, 0x38, 0x29, 0x01]),
+					("label", attributes, &[0x81, 0x62, b'h', b'i']),
+					("nothing", attributes, &[0x80]),
+				],
+			);
+			let mut invoker = Invoker::take().unwrap();
+			assert_eq!(
+				invoker.call_component::<i64>(&address, "number", None),
+				Ok(-42)
+			);
+			assert_eq!(
+				invoker.call_component::<String>(&address, "label", None),
+				Ok(String::from("hi"))
+			);
+			assert_eq!(
+				invoker.call_component::<String>(&address, "number", None),
+				Err(Error::CborDecode)
+			);
+			assert_eq!(
+				invoker.call_component::<i64>(&address, "nothing", None),
+				Err(Error::CborDecode)
+			);
+		}
+
+		#[test]
+		fn component_method_now_short_buffer() {
+			mock::reset();
+			let address = Address::from_bytes([0x41; 16]);
+			register_result_component(address);
+			let mut invoker = Invoker::take().unwrap();
+			let mut buffer = [0_u8; 4];
+			assert_eq!(
+				invoker.component_method_now(&address, "small", None, &mut buffer),
+				Ok(Some(2))
+			);
+			assert_eq!(
+				invoker.component_method_now(&address, "large", None, &mut buffer),
+				Err(Error::BufferTooShort)
+			);
+			assert_eq!(
+				invoker.component_method_now(&address, "slow", None, &mut buffer),
+				Ok(None)
+			);
+		}
+
+		#[test]
+		fn listers_take_once() {
+			let listers = Listers::take();
+			assert!(listers.is_some());
+			assert!(Listers::take().is_none());
+			assert!(Lister::take().is_none());
+		}
+
+		#[test]
+		fn listers_take_all_or_nothing() {
+			let acl_lister = AclLister::take().unwrap();
+			assert!(Listers::take().is_none());
+			assert!(Lister::take().is_some());
+			assert!(MethodLister::take().is_some());
+			drop(acl_lister);
+		}
+
+		#[test]
+		fn environment_take_once() {
+			let environment = Environment::take();
+			assert!(environment.is_some());
+			assert!(Environment::take().is_none());
+			assert!(Invoker::take().is_none());
+			assert!(Listers::take().is_none());
+		}
+
+		#[test]
+		fn environment_take_all_or_nothing() {
+			let method_lister = MethodLister::take().unwrap();
+			assert!(Environment::take().is_none());
+			assert!(Invoker::take().is_some());
+			assert!(Lister::take().is_some());
+			assert!(AclLister::take().is_some());
+			drop(method_lister);
+		}
+
+		#[test]
+		fn result_descriptors_two_and_malformed() {
+			mock::reset();
+			let (a, b) = (open_descriptor(), open_descriptor());
+			// [39(a), "x", 39(b)]
+			let buffer = [0x83, 0xD8, 0x27, a, 0x61, b'x', 0xD8, 0x27, b];
+			// SAFETY: The descriptors were opened above and nothing else owns them.
+			let mut iter = unsafe { result_descriptors(&buffer) }.unwrap();
+			let first = iter.next().unwrap().unwrap();
+			assert_eq!(first.as_raw(), u32::from(a));
+			assert_eq!(iter.next().unwrap().unwrap_err(), Error::CborDecode);
+			let second = iter.next().unwrap().unwrap();
+			assert_eq!(second.as_raw(), u32::from(b));
+			assert!(iter.next().is_none());
+			first.close();
+			second.close();
+			assert_eq!(mock::open_descriptors(), 0);
+		}
+
+		#[test]
+		fn result_descriptors_rejects_duplicate() {
+			mock::reset();
+			let raw = open_descriptor();
+			// [39(raw), 39(raw)]
+			let buffer = [0x82, 0xD8, 0x27, raw, 0xD8, 0x27, raw];
+			// SAFETY: The descriptor was opened above and nothing else owns it.
+			let mut iter = unsafe { result_descriptors(&buffer) }.unwrap();
+			let first = iter.next().unwrap().unwrap();
+			assert_eq!(first.as_raw(), u32::from(raw));
+			assert_eq!(
+				iter.next().unwrap().unwrap_err(),
+				Error::DuplicateDescriptor
+			);
+			assert!(iter.next().is_none());
+		}
+
+		#[test]
+		fn result_descriptors_rejects_duplicate_beyond_capacity() {
+			mock::reset();
+			let count = SEEN_CAPACITY + 2;
+			let raws: Vec<u8> = (0..count).map(|_| open_descriptor()).collect();
+			// [39(raws[0]), …, 39(raws[count - 1]), 39(raws[0]), 39(raws[count - 1])]
+			let mut buffer = vec![0x80 + u8::try_from(count + 2).unwrap()];
+			for &raw in raws.iter().chain([raws[0], raws[count - 1]].iter()) {
+				buffer.extend_from_slice(&[0xD8, 0x27, raw]);
+			}
+			// SAFETY: The descriptors were opened above and nothing else owns them.
+			let iter = unsafe { result_descriptors(&buffer) }.unwrap();
+			let results: Vec<Result<Owned>> = iter.collect();
+			assert_eq!(results.len(), count + 2);
+			for (result, &raw) in results.iter().zip(raws.iter()) {
+				assert_eq!(result.as_ref().unwrap().as_raw(), u32::from(raw));
+			}
+			for result in &results[count..] {
+				assert_eq!(result.as_ref().unwrap_err(), &Error::DuplicateDescriptor);
+			}
+			for result in results.into_iter().take(count) {
+				result.unwrap().close();
+			}
+		}
+
+		#[test]
+		fn index_params_encode_errors() {
+			struct Refuse;
+
+			impl<C> Encode<C> for Refuse {
+				fn encode<W: minicbor::encode::Write>(
+					&self,
+					_: &mut Encoder<W>,
+					_: &mut C,
+				) -> core::result::Result<(), minicbor::encode::Error<W::Error>> {
+					Err(minicbor::encode::Error::message("refused"))
+				}
+			}
+
+			mock::reset();
+			// SAFETY: The descriptor was just opened and nothing else owns it.
+			let descriptor = unsafe { Owned::new(mock::new_descriptor()) };
+			let mut invoker = Invoker::take().unwrap();
+			let mut scratch = [0_u8; 16];
+			assert_eq!(
+				invoker
+					.index_read(&descriptor, &Refuse, &mut scratch)
+					.unwrap_err(),
+				Error::CborDecode
+			);
+			assert_eq!(
+				invoker
+					.index_write(&descriptor, &1_u8, &Refuse, &mut scratch)
+					.unwrap_err(),
+				Error::CborDecode
+			);
+			assert_eq!(
+				invoker
+					.index_read(&descriptor, &"long key", &mut scratch[..4])
+					.unwrap_err(),
+				Error::BufferTooShort
+			);
+			assert_eq!(
+				invoker
+					.index_write(&descriptor, &1_u8, &"long value", &mut scratch[..4])
+					.unwrap_err(),
+				Error::BufferTooShort
+			);
+			descriptor.close();
+		}
+
+		#[test]
+		fn result_reader_rejects_duplicate_descriptor() {
+			mock::reset();
+			let raw = mock::new_descriptor();
+			let raw = u8::try_from(raw).unwrap();
+			// [39(raw), 39(raw)]
+			let buffer = [0x82, 0xD8, 0x27, raw, 0xD8, 0x27, raw];
+			let mut reader = ResultReader::new(&buffer).unwrap();
+			// SAFETY: The descriptor was opened above and nothing else owns it.
+			let first = unsafe { reader.next_descriptor() }.unwrap();
+			assert_eq!(first.as_raw(), u32::from(raw));
+			// SAFETY: As above; the duplicate is rejected rather than owned.
+			assert_eq!(
+				unsafe { reader.next_descriptor() }.unwrap_err(),
+				Error::DuplicateDescriptor
+			);
+			assert_eq!(reader.remaining(), 0);
+		}
+
+		#[cfg(feature = "debug-calls")]
+		#[test]
+		fn method_call_debug_includes_method() {
+			let address = Address::from_bytes([0x35; 16]);
+			mock::register_component(address, "example", &[]);
+			let attributes = MethodAttributes {
+				direct: false,
+				getter: false,
+				setter: false,
+			};
+			mock::set_method(&address, "slowMethod", attributes, |_| Ok(vec![0x80]));
+			let mut invoker = Invoker::take().unwrap();
+			let (_, call) = invoker
+				.component_method(&address, "slowMethod", None)
+				.unwrap();
+			assert!(format!("{call:?}").contains("\"slowMethod\""));
+		}
+	}
+}
@@ -2,12 +2,22 @@
 
 use super::error::{Error, Result};
 use super::helpers::{call_buffer, call_buffer_len, call_buffer_str, call_string};
+use super::sys::computer as sys;
 use super::Address;
 use crate::panic_or_trap;
+use core::fmt::{Display, Formatter};
+use core::marker::PhantomData;
 use core::num::NonZeroUsize;
-use oc_wasm_sys::computer as sys;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use minicbor::data::Type;
+use minicbor::decode::Decoder;
+use minicbor::encode::Encoder;
 use ordered_float::NotNan;
 
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
 /// Returns the amount of world time the computer has been running, in seconds.
 #[must_use = "This function is only useful for its return value"]
 pub fn uptime() -> NotNan<f64> {
@@ -59,6 +69,23 @@ pub fn installed_ram() -> u32 {
 	unsafe { sys::installed_ram() }
 }
 
+/// Returns the amount, in kibibytes, of RAM installed in the computer.
+///
+/// Any fraction of a kibibyte is discarded.
+#[must_use = "This function is only useful for its return value"]
+pub fn installed_ram_kib() -> u32 {
+	installed_ram() / 1024
+}
+
+/// Returns the amount, in bytes, of RAM installed in the computer, as a `usize`.
+///
+/// This is convenient for comparing against buffer lengths.
+#[must_use = "This function is only useful for its return value"]
+pub fn installed_ram_usize() -> usize {
+	// Cast from u32 to usize is lossless because Wasm is a 32-bit target (or more).
+	installed_ram() as usize
+}
+
 /// Returns the amount, in bytes, of free RAM in the computer.
 #[must_use = "This function is only useful for its return value"]
 pub fn free_ram() -> u32 {
@@ -66,6 +93,95 @@ pub fn free_ram() -> u32 {
 	unsafe { sys::free_ram() }
 }
 
+/// Returns the fraction of the computer’s installed RAM that is in use.
+///
+/// The `used_bytes` parameter is the number of bytes of memory in use, as reported by the
+/// application’s allocator. The result is `used_bytes` divided by [`installed_ram`](installed_ram),
+/// clamped to the range 0 to 1. If the computer reports no installed RAM, 1 is returned.
+#[must_use = "This function is only useful for its return value"]
+pub fn ram_pressure(used_bytes: u32) -> NotNan<f64> {
+	let installed = installed_ram();
+	let ratio = if installed == 0 {
+		1.0
+	} else {
+		(f64::from(used_bytes) / f64::from(installed)).min(1.0)
+	};
+	// SAFETY: ratio is either 1 or the quotient of two finite numbers with a nonzero divisor, so it
+	// cannot be NaN.
+	unsafe { NotNan::new_unchecked(ratio) }
+}
+
+/// Returns the fraction of the time the computer has been running that it has spent using the CPU.
+///
+/// The result is [`cpu_time`](cpu_time) divided by [`uptime`](uptime), clamped to the range 0
+/// to 1. Immediately after startup, when the uptime is still zero, 0 is returned.
+#[must_use = "This function is only useful for its return value"]
+pub fn cpu_load() -> NotNan<f64> {
+	let uptime = uptime().into_inner();
+	let ratio = if uptime > 0.0 {
+		cpu_time().into_inner() / uptime
+	} else {
+		0.0
+	};
+	// The quotient is only NaN if both times are infinite, which should never happen.
+	NotNan::new(ratio.clamp(0.0, 1.0)).unwrap_or_default()
+}
+
+/// A monotonic clock based on the computer’s [`uptime`](uptime).
+///
+/// This is the OC-Wasm equivalent of the clock behind `std::time::Instant`. It has no state; it
+/// exists only to give [`now`](Clock::now) a recognizable name.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Clock;
+
+impl Clock {
+	/// Returns the current instant.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn now() -> Instant {
+		Instant(uptime())
+	}
+}
+
+/// A point in time, measured by the computer’s [`uptime`](uptime).
+///
+/// Instants are only meaningful while the computer keeps running; uptime starts again from zero
+/// when the computer is rebooted.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Instant(NotNan<f64>);
+
+impl Instant {
+	/// Creates an instant from a number of seconds of uptime.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn from_uptime(seconds: NotNan<f64>) -> Self {
+		Self(seconds)
+	}
+
+	/// Returns the number of seconds of uptime at this instant.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn uptime(&self) -> NotNan<f64> {
+		self.0
+	}
+
+	/// Returns the amount of time elapsed from `earlier` to this instant.
+	///
+	/// If `earlier` is actually later than this instant, zero is returned.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn duration_since(&self, earlier: Self) -> Duration {
+		let seconds = self.0.into_inner() - earlier.0.into_inner();
+		if seconds > 0.0 {
+			Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+		} else {
+			Duration::ZERO
+		}
+	}
+
+	/// Returns the amount of time elapsed since this instant.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn elapsed(&self) -> Duration {
+		Clock::now().duration_since(*self)
+	}
+}
+
 /// Pushes a signal to the signal queue.
 ///
 /// The `signal` parameter contains a CBOR-encoded array representing the signal, which must be a
@@ -73,8 +189,8 @@ pub fn free_ram() -> u32 {
 /// be a string containing the signal name.
 ///
 /// # Errors
-/// * [`CborDecode`](Error::CborDecode) is returned if the `params` pointer is present but contains
-///   an invalid or unsupported CBOR sequence.
+/// * [`CborDecode`](Error::CborDecode) is returned if `signal` contains an invalid or unsupported
+///   CBOR sequence.
 /// * [`QueueFull`](Error::QueueFull) is returned if the computer’s signal queue is full.
 pub fn push_signal(signal: &[u8]) -> Result<()> {
 	Error::from_i32(
@@ -84,6 +200,177 @@ pub fn push_signal(signal: &[u8]) -> Result<()> {
 	Ok(())
 }
 
+/// Pushes a signal to the signal queue, treating a full queue as a soft failure.
+///
+/// This is the same as [`push_signal`](push_signal), except that if the signal queue is full,
+/// `Ok(false)` is returned instead of an error, so that a producer can back off and try again on
+/// a later timeslice. On success, `Ok(true)` is returned.
+///
+/// # Errors
+/// * [`CborDecode`](Error::CborDecode) is returned if `signal` contains an invalid or unsupported
+///   CBOR sequence.
+pub fn push_signal_try(signal: &[u8]) -> Result<bool> {
+	match push_signal(signal) {
+		Ok(()) => Ok(true),
+		Err(Error::QueueFull) => Ok(false),
+		Err(e) => Err(e),
+	}
+}
+
+/// Pushes a timer signal to the signal queue.
+///
+/// The `name` parameter is the name of the signal. The `fire_at_uptime` parameter is the value of
+/// [`uptime`](uptime) at which the timer should be considered due. The `scratch` parameter is a
+/// buffer used to hold the encoded signal.
+///
+/// The pushed signal has a single parameter, `fire_at_uptime`. When it is later popped via
+/// [`pull_signal`](pull_signal), [`is_timer_due`](is_timer_due) can be used to check whether the
+/// timer has expired.
+///
+/// # Errors
+/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `scratch` is not large enough to
+///   hold the encoded signal.
+/// * [`QueueFull`](Error::QueueFull) is returned if the computer’s signal queue is full.
+pub fn push_timer(name: &str, fire_at_uptime: f64, scratch: &mut [u8]) -> Result<()> {
+	let mut encoder = Encoder::new(&mut *scratch);
+	encoder
+		.array(2)
+		.and_then(|e| e.str(name))
+		.and_then(|e| e.f64(fire_at_uptime))
+		.map_err(|_| Error::BufferTooShort)?;
+	push_signal(scratch)
+}
+
+/// Checks whether a timer signal pushed by [`push_timer`](push_timer) is due.
+///
+/// The `signal` parameter is the signal data, as returned by [`pull_signal`](pull_signal).
+///
+/// If the signal is a timer signal, `true` is returned if the current [`uptime`](uptime) is at
+/// least the timer’s firing time, or `false` if not. If the signal is not a timer signal (its
+/// parameters are not a single number), `None` is returned. The signal name is not checked.
+#[must_use = "This function is only useful for its return value"]
+pub fn is_timer_due(signal: &[u8]) -> Option<bool> {
+	let mut decoder = Decoder::new(signal);
+	decoder.str().ok()?;
+	if decoder.array().ok()? != Some(1) {
+		return None;
+	}
+	let fire_at_uptime = match decoder.datatype().ok()? {
+		Type::F16 | Type::F32 | Type::F64 => decoder.f64().ok()?,
+		// Timer values are uptimes, which are small enough to be represented exactly.
+		#[allow(clippy::cast_precision_loss)]
+		Type::U8 | Type::U16 | Type::U32 | Type::U64 => decoder.u64().ok()? as f64,
+		_ => return None,
+	};
+	Some(uptime().into_inner() >= fire_at_uptime)
+}
+
+/// A fixed-capacity buffer of outbound signals waiting to be pushed to the signal queue.
+///
+/// A value of this type holds up to `N` signals, each of which is a CBOR-encoded array of up to
+/// `L` bytes, as accepted by [`push_signal`](push_signal). It is useful when the computer’s signal
+/// queue is full: signals can be buffered locally and pushed later, in the order in which they
+/// were buffered, without requiring dynamic memory allocation.
+#[derive(Clone, Debug)]
+pub struct SignalRing<const N: usize, const L: usize> {
+	/// The storage for the signals.
+	data: [[u8; L]; N],
+
+	/// The lengths of the signals in `data`.
+	lengths: [usize; N],
+
+	/// The index in `data` of the oldest buffered signal.
+	head: usize,
+
+	/// The number of buffered signals.
+	count: usize,
+}
+
+impl<const N: usize, const L: usize> SignalRing<N, L> {
+	/// Creates an empty ring.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn new() -> Self {
+		Self {
+			data: [[0; L]; N],
+			lengths: [0; N],
+			head: 0,
+			count: 0,
+		}
+	}
+
+	/// Returns the number of buffered signals.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn len(&self) -> usize {
+		self.count
+	}
+
+	/// Returns whether there are no buffered signals.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn is_empty(&self) -> bool {
+		self.count == 0
+	}
+
+	/// Adds a signal to the end of the ring.
+	///
+	/// The `signal` parameter contains a CBOR-encoded array representing the signal, as accepted by
+	/// [`push_signal`](push_signal). It is not validated until it is flushed.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `signal` is longer than `L`
+	///   bytes.
+	/// * [`QueueFull`](Error::QueueFull) is returned if the ring already holds `N` signals.
+	pub fn push(&mut self, signal: &[u8]) -> Result<()> {
+		if signal.len() > L {
+			Err(Error::BufferTooShort)
+		} else if self.count == N {
+			Err(Error::QueueFull)
+		} else {
+			let index = (self.head + self.count) % N;
+			self.data[index][..signal.len()].copy_from_slice(signal);
+			self.lengths[index] = signal.len();
+			self.count += 1;
+			Ok(())
+		}
+	}
+
+	/// Pushes as many buffered signals as possible to the computer’s signal queue.
+	///
+	/// Signals are pushed in the order in which they were added to the ring. Pushing stops when the
+	/// ring is empty or when the computer’s signal queue is full; in the latter case, the remaining
+	/// signals stay in the ring for a later attempt.
+	///
+	/// On success, the number of signals pushed is returned.
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if a buffered signal contains an invalid or
+	///   unsupported CBOR sequence.
+	///
+	/// On error, the offending signal is discarded, because retrying it would fail again; any
+	/// signals after it remain in the ring.
+	pub fn flush(&mut self) -> Result<usize> {
+		let mut pushed = 0;
+		while self.count != 0 {
+			let signal = &self.data[self.head][..self.lengths[self.head]];
+			match push_signal(signal) {
+				Err(Error::QueueFull) => break,
+				result => {
+					self.head = (self.head + 1) % N;
+					self.count -= 1;
+					result?;
+					pushed += 1;
+				}
+			}
+		}
+		Ok(pushed)
+	}
+}
+
+impl<const N: usize, const L: usize> Default for SignalRing<N, L> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 /// Returns the length, in bytes, of the next signal in the signal queue.
 ///
 /// If there is no next entry, `None` is returned.
@@ -93,13 +380,25 @@ pub fn push_signal(signal: &[u8]) -> Result<()> {
 /// should be impossible due to the type system.
 #[must_use = "This function is only useful for its return value"]
 pub fn pull_signal_length() -> Option<NonZeroUsize> {
-	let len =
-		// SAFETY: pull_signal permits null.
-		unsafe{call_buffer_len(sys::pull_signal)};
 	// Can’t fail because pull_signal can only fail due to MemoryFault or StringDecode, and
 	// Error::from_isize already treats those as unreachable.
-	let len = len.unwrap_or_else(|_| panic_or_trap!("unreachable"));
-	NonZeroUsize::new(len)
+	try_pull_signal_length().unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e))
+}
+
+/// Returns the length, in bytes, of the next signal in the signal queue, reporting syscall
+/// failures.
+///
+/// This is the same as [`pull_signal_length`](pull_signal_length), except that if the underlying
+/// syscall fails, the error is returned instead of panicking. Such failures should be impossible,
+/// but this can help to diagnose a mismatch between OC-Wasm-Safe and the version of OC-Wasm in
+/// use.
+///
+/// # Errors
+/// * [`Unknown`](Error::Unknown) or another error is returned if the underlying syscall fails.
+pub fn try_pull_signal_length() -> Result<Option<NonZeroUsize>> {
+	// SAFETY: pull_signal permits null.
+	let len = unsafe { call_buffer_len(sys::pull_signal) }?;
+	Ok(NonZeroUsize::new(len))
 }
 
 /// Pops a signal from the signal queue.
@@ -123,10 +422,373 @@ pub fn pull_signal(buffer: &mut [u8]) -> Result<Option<&mut [u8]>> {
 	Ok(if ret.is_empty() { None } else { Some(ret) })
 }
 
+/// Pops a signal from the signal queue into a newly allocated buffer.
+///
+/// If there is a signal pending, a buffer of exactly the right size is allocated, the signal data
+/// is written to it as described in [`pull_signal`](pull_signal), the signal is removed from the
+/// queue, and the buffer is returned. If not, `None` is returned.
+///
+/// # Errors
+/// * [`Unknown`](Error::Unknown) or another error is returned if the underlying syscall fails.
+#[cfg(feature = "alloc")]
+pub fn pull_signal_vec() -> Result<Option<Vec<u8>>> {
+	let Some(len) = try_pull_signal_length()? else {
+		return Ok(None);
+	};
+	let mut buffer = vec![0; len.get()];
+	let written = match pull_signal(&mut buffer)? {
+		Some(signal) => signal.len(),
+		None => return Ok(None),
+	};
+	buffer.truncate(written);
+	Ok(Some(buffer))
+}
+
+/// A view of a signal popped from the signal queue.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Signal<'buffer> {
+	/// The name of the signal.
+	name: &'buffer str,
+
+	/// The CBOR-encoded array of additional signal parameters.
+	params: &'buffer [u8],
+}
+
+impl<'buffer> Signal<'buffer> {
+	/// Parses signal data.
+	///
+	/// The `signal` parameter is the signal data, as returned by [`pull_signal`](pull_signal).
+	///
+	/// # Errors
+	/// * [`CborDecode`](Error::CborDecode) is returned if `signal` does not start with the
+	///   signal name as a CBOR text string.
+	pub fn new(signal: &'buffer [u8]) -> Result<Self> {
+		let mut decoder = Decoder::new(signal);
+		let name = decoder.str()?;
+		Ok(Self {
+			name,
+			params: &signal[decoder.position()..],
+		})
+	}
+
+	/// Returns the name of the signal.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn name(&self) -> &'buffer str {
+		self.name
+	}
+
+	/// Returns the additional signal parameters, as a CBOR-encoded array.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn params(&self) -> &'buffer [u8] {
+		self.params
+	}
+}
+
+/// Returns an object that pops the signals currently in the signal queue one at a time.
+///
+/// The `buffer` parameter identifies where to store each signal’s data. It is reused for every
+/// signal, so each [`Signal`](Signal) must be dropped before the next one is popped.
+#[must_use = "This function is only useful for its return value"]
+pub fn drain_signals(buffer: &mut [u8]) -> SignalDrain<'_> {
+	SignalDrain { buffer }
+}
+
+/// An object that pops signals from the signal queue into a shared buffer.
+///
+/// A value of this type is created by [`drain_signals`](drain_signals). It cannot implement
+/// `Iterator`, because each signal it yields borrows the buffer that the next signal will be
+/// written into; instead, [`next_signal`](SignalDrain::next_signal) is called repeatedly.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SignalDrain<'buffer> {
+	/// The buffer to write each signal into.
+	buffer: &'buffer mut [u8],
+}
+
+impl SignalDrain<'_> {
+	/// Pops the next signal from the signal queue.
+	///
+	/// If there is a signal pending, it is removed from the queue and returned. If the queue is
+	/// empty, `None` is returned.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if the buffer is not large enough
+	///   to hold the signal data. The signal remains in the queue, so the caller can fetch it
+	///   with a larger buffer via [`pull_signal`](pull_signal).
+	/// * [`CborDecode`](Error::CborDecode) is returned if the signal does not start with its name.
+	///   The signal has been removed from the queue.
+	pub fn next_signal(&mut self) -> Result<Option<Signal<'_>>> {
+		match pull_signal(self.buffer)? {
+			Some(signal) => Ok(Some(Signal::new(signal)?)),
+			None => Ok(None),
+		}
+	}
+}
+
+/// Runs a function with access to the waker registered by
+/// [`register_signal_waker`](register_signal_waker).
+///
+/// `f` must not clone, drop, or wake a waker, because those run executor code that may call back
+/// into this module; it may only compare wakers and move them in and out of the slot.
+fn with_signal_waker<R>(f: impl FnOnce(&mut Option<Waker>) -> R) -> R {
+	#[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
+	{
+		static mut WAKER: Option<Waker> = None;
+		// SAFETY: Wasm doesn’t have threads, so only one caller can get here at a time, and `f`
+		// runs no executor code, so it cannot re-enter this function while the reference is live.
+		unsafe { f(&mut *core::ptr::addr_of_mut!(WAKER)) }
+	}
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	{
+		std::thread_local! {
+			static WAKER: core::cell::RefCell<Option<Waker>> = const { core::cell::RefCell::new(None) };
+		}
+		WAKER.with(|waker| f(&mut waker.borrow_mut()))
+	}
+}
+
+/// Registers a waker to be woken when a signal may have arrived.
+///
+/// Only one waker is held at a time; registering a new one replaces any previous one, unless the
+/// two would wake the same task. The waker is not woken by this crate on its own: OC-Wasm is
+/// single-threaded and cooperative, so nothing can happen while the application is running, and
+/// a new signal can only arrive between timeslices. Therefore an application that uses this
+/// function must arrange for its top-level poll loop to call
+/// [`wake_signal_waker`](wake_signal_waker) at the start of each timeslice, before polling its
+/// tasks.
+pub fn register_signal_waker(waker: &Waker) {
+	if with_signal_waker(|slot| slot.as_ref().is_some_and(|i| i.will_wake(waker))) {
+		return;
+	}
+	// Cloning the new waker and dropping the old one both run executor code, so they are done
+	// outside with_signal_waker.
+	let waker = waker.clone();
+	let old = with_signal_waker(|slot| slot.replace(waker));
+	drop(old);
+}
+
+/// Wakes and removes the waker registered by [`register_signal_waker`](register_signal_waker), if
+/// any.
+///
+/// This should be called by the application’s top-level poll loop once per timeslice, before
+/// polling its tasks.
+pub fn wake_signal_waker() {
+	// The waker is taken out before being woken, so that waking it may register a new one.
+	if let Some(waker) = with_signal_waker(Option::take) {
+		waker.wake();
+	}
+}
+
+/// Pops a signal from the signal queue, or registers to be woken if none is available.
+///
+/// The `cx` parameter is the context of the task waiting for the signal. The `buffer` parameter
+/// identifies where to store the signal data.
+///
+/// If there is a signal pending, it is removed from the queue and returned. If the queue is empty,
+/// the task’s waker is registered via [`register_signal_waker`](register_signal_waker) and
+/// `Pending` is returned; the task will be woken on the next call to
+/// [`wake_signal_waker`](wake_signal_waker).
+///
+/// # Errors
+/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to hold
+///   the signal data. The signal remains in the queue.
+/// * [`CborDecode`](Error::CborDecode) is returned if the signal does not start with its name.
+///   The signal has been removed from the queue.
+///
+/// # Example
+/// A task that waits for the next signal and logs its name:
+/// ```no_run
+/// use core::future::poll_fn;
+/// use oc_wasm_safe::computer::{debug, poll_signal};
+/// use oc_wasm_safe::error::Result;
+///
+/// async fn log_next_signal(buffer: &mut [u8]) -> Result<()> {
+///     poll_fn(|cx| poll_signal(cx, buffer).map(|signal| signal.map(|s| debug(s.name())))).await
+/// }
+/// ```
+pub fn poll_signal<'buffer>(
+	cx: &mut Context<'_>,
+	buffer: &'buffer mut [u8],
+) -> Poll<Result<Signal<'buffer>>> {
+	match pull_signal(buffer) {
+		Ok(Some(signal)) => Poll::Ready(Signal::new(signal)),
+		Ok(None) => {
+			register_signal_waker(cx.waker());
+			Poll::Pending
+		}
+		Err(e) => Poll::Ready(Err(e)),
+	}
+}
+
+/// A holder that allows the next signal to be examined before it is consumed.
+///
+/// OC-Wasm has no system call to look at a signal without removing it from the queue. This type
+/// works around that at the Rust level: the first time the next signal is examined (via
+/// [`peek`](PendingSignal::peek) or [`peek_name`](PendingSignal::peek_name)), it is popped from
+/// the queue into an internal buffer of `N` bytes and held there. The held signal is then returned
+/// by the next call to [`pull`](PendingSignal::pull) before anything else is popped from the
+/// queue, so as long as all signals are pulled through the same holder, the order in which they
+/// are seen is unchanged.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingSignal<const N: usize> {
+	/// The buffer holding the signal data.
+	data: [u8; N],
+
+	/// The length of the held signal, or zero if no signal is held.
+	len: usize,
+}
+
+impl<const N: usize> PendingSignal<N> {
+	/// Creates a holder with no signal held.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn new() -> Self {
+		Self {
+			data: [0; N],
+			len: 0,
+		}
+	}
+
+	/// Returns whether a signal is currently held.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn is_held(&self) -> bool {
+		self.len != 0
+	}
+
+	/// Returns the next signal without consuming it.
+	///
+	/// If no signal is held, one is popped from the signal queue and held. If the queue is empty,
+	/// `None` is returned.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if the next signal is larger than
+	///   `N` bytes. The signal remains in the queue.
+	/// * [`CborDecode`](Error::CborDecode) is returned if the signal does not start with its name.
+	///   The signal is held, and can still be pulled.
+	pub fn peek(&mut self) -> Result<Option<Signal<'_>>> {
+		if self.len == 0 {
+			match pull_signal(&mut self.data)? {
+				Some(signal) => self.len = signal.len(),
+				None => return Ok(None),
+			}
+		}
+		Ok(Some(Signal::new(&self.data[..self.len])?))
+	}
+
+	/// Returns the name of the next signal without consuming it.
+	///
+	/// This is equivalent to [`peek`](PendingSignal::peek) followed by
+	/// [`Signal::name`](Signal::name).
+	///
+	/// # Errors
+	/// Any error that [`peek`](PendingSignal::peek) can return.
+	pub fn peek_name(&mut self) -> Result<Option<&str>> {
+		Ok(self.peek()?.map(|signal| signal.name()))
+	}
+
+	/// Consumes the next signal.
+	///
+	/// The `buffer` parameter identifies where to store the signal data.
+	///
+	/// If a signal is held, it is copied into `buffer` and released; otherwise, this is equivalent
+	/// to [`pull_signal`](pull_signal).
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the signal data. The signal remains held or in the queue.
+	pub fn pull<'buffer>(
+		&mut self,
+		buffer: &'buffer mut [u8],
+	) -> Result<Option<&'buffer mut [u8]>> {
+		if self.len == 0 {
+			pull_signal(buffer)
+		} else {
+			let target = buffer.get_mut(..self.len).ok_or(Error::BufferTooShort)?;
+			target.copy_from_slice(&self.data[..self.len]);
+			self.len = 0;
+			Ok(Some(target))
+		}
+	}
+
+	/// Discards the held signal, if any.
+	///
+	/// If no signal is held, nothing happens; the signal queue is not touched.
+	pub fn discard(&mut self) {
+		self.len = 0;
+	}
+}
+
+impl<const N: usize> Default for PendingSignal<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The contents of a `redstone_changed` signal.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RedstoneChanged {
+	/// The address of the redstone component that detected the change.
+	pub address: Address,
+
+	/// The side on which the change occurred.
+	pub side: u8,
+
+	/// The signal strength before the change.
+	pub old_value: u32,
+
+	/// The signal strength after the change.
+	pub new_value: u32,
+
+	/// The colour of the bundled cable channel that changed, or `None` for a plain redstone
+	/// signal.
+	pub color: Option<u8>,
+}
+
+/// Decodes a `redstone_changed` signal.
+///
+/// The `signal` parameter is the signal data, as returned by [`pull_signal`](pull_signal).
+///
+/// If the signal is a `redstone_changed` signal, its contents are returned. If it is some other
+/// signal, `None` is returned.
+///
+/// # Errors
+/// * [`CborDecode`](Error::CborDecode) is returned if `signal` is not a valid signal, or if it is
+///   a `redstone_changed` signal but its parameters do not have the expected types.
+pub fn decode_redstone_changed(signal: &[u8]) -> Result<Option<RedstoneChanged>> {
+	fn decode(
+		d: &mut Decoder<'_>,
+	) -> core::result::Result<RedstoneChanged, minicbor::decode::Error> {
+		let len = d.array()?;
+		if !matches!(len, Some(4 | 5)) {
+			return Err(minicbor::decode::Error::message(
+				"expected four or five redstone_changed parameters",
+			));
+		}
+		let address = d.decode()?;
+		let side = d.u8()?;
+		let old_value = d.u32()?;
+		let new_value = d.u32()?;
+		let color = if len == Some(5) { Some(d.u8()?) } else { None };
+		Ok(RedstoneChanged {
+			address,
+			side,
+			old_value,
+			new_value,
+			color,
+		})
+	}
+
+	let mut decoder = Decoder::new(signal);
+	if decoder.str().map_err(|_| Error::CborDecode)? != "redstone_changed" {
+		return Ok(None);
+	}
+	Ok(Some(decode(&mut decoder).map_err(|_| Error::CborDecode)?))
+}
+
 /// Begins iteration over the computer’s access control list.
 ///
 /// Iteration over the access control list is not reentrant. Concurrent software must ensure that
-/// only one access control list iteration at a time is attempted.
+/// only one access control list iteration at a time is attempted. This function and
+/// [`acl_next`](acl_next) are the unchecked low-level interface; [`AclLister`](AclLister) uses
+/// the borrow checker to enforce this requirement instead.
 pub fn acl_start() {
 	unsafe { sys::acl_start() }
 }
@@ -145,7 +807,7 @@ pub fn acl_next_len() -> Option<NonZeroUsize> {
 		unsafe{call_buffer_len(sys::acl_next)};
 	// Can’t fail because acl_next can only fail due to MemoryFault or StringDecode, and
 	// Error::from_isize already treats those as unreachable.
-	let len = len.unwrap_or_else(|_| panic_or_trap!("unreachable"));
+	let len = len.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e));
 	NonZeroUsize::new(len)
 }
 
@@ -168,6 +830,148 @@ pub fn acl_next(buffer: &mut [u8]) -> Result<Option<&mut str>> {
 	Ok(if s.is_empty() { None } else { Some(s) })
 }
 
+/// An object that is capable of listing the users in the computer’s access control list.
+///
+/// Because only one access control list iteration can be in progress at a time, only one value of
+/// this type can exist, and each iteration borrows it mutably. This is the same pattern as
+/// [`component::Lister`](super::component::Lister).
+///
+/// Starting a second iteration while the first is still in use does not compile:
+/// ```compile_fail,E0499
+/// use oc_wasm_safe::computer::AclLister;
+///
+/// let mut lister = AclLister::take().unwrap();
+/// let first = lister.start();
+/// let second = lister.start();
+/// let _ = first.next_len();
+/// ```
+pub struct AclLister(());
+
+singleton!(ACL_LISTER: AclLister = AclLister(()));
+
+impl AclLister {
+	/// Returns the lister.
+	///
+	/// This function can only be called once in the lifetime of the application. On the second and
+	/// subsequent calls, it will return `None`.
+	#[must_use = "An AclLister can only be taken once. It needs to be saved. Discarding it means it is impossible to ever safely list users."]
+	pub fn take() -> Option<Self> {
+		take_singleton!(ACL_LISTER)
+	}
+
+	/// Gives the lister back, so that the next call to [`take`](AclLister::take) returns it again.
+	pub(crate) fn restore(self) {
+		restore_singleton!(ACL_LISTER, self);
+	}
+
+	/// Begins iteration over the computer’s access control list.
+	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
+	pub fn start(&mut self) -> AclIteration<'_> {
+		acl_start();
+		AclIteration(PhantomData)
+	}
+}
+
+/// An in-progress access control list iteration.
+///
+/// The `'lister` lifetime parameter is the lifetime of the ACL lister that is performing the
+/// iteration.
+#[must_use = "Starting an ACL iteration is only useful if you read the results."]
+pub struct AclIteration<'lister>(PhantomData<&'lister mut AclLister>);
+
+impl AclIteration<'_> {
+	/// Returns the length, in bytes, of the Minecraft username of the next allowed user.
+	///
+	/// If there is no next entry, `None` is returned.
+	///
+	/// # Panics
+	/// This function panics if the underlying syscall fails, because the only reasons it could
+	/// fail should be impossible due to the type system.
+	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn next_len(&self) -> Option<NonZeroUsize> {
+		acl_next_len()
+	}
+
+	/// Returns the Minecraft username of the next allowed user.
+	///
+	/// The `buffer` parameter identifies where to store the next username.
+	///
+	/// If there is a next entry, the username is written to `buffer`, a string slice referring to
+	/// it is returned, and the iteration is advanced. If not, `None` is returned.
+	///
+	/// # Errors
+	/// * [`BufferTooShort`](Error::BufferTooShort) is returned if `buffer` is not large enough to
+	///   hold the username.
+	///
+	/// On error, the iteration does not advance.
+	#[allow(clippy::unused_self)] // Not used for its value, but used for its lifetime.
+	pub fn next<'buffer>(&mut self, buffer: &'buffer mut [u8]) -> Result<Option<&'buffer mut str>> {
+		acl_next(buffer)
+	}
+}
+
+/// A Minecraft username that has been checked for validity.
+///
+/// Minecraft usernames are 1 to 16 characters long and consist only of ASCII letters, digits, and
+/// underscores. A value of this type rules out anything else at construction time, rather than
+/// letting an invalid name surface later as [`Other`](Error::Other) from [`add_user`](add_user) or
+/// [`remove_user`](remove_user). A value of this type dereferences to `str`, so it can be passed to
+/// those functions directly.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Username<'a>(&'a str);
+
+impl<'a> Username<'a> {
+	/// Checks and wraps a username.
+	///
+	/// # Errors
+	/// * [`BadParameters`](Error::BadParameters) is returned if `name` is empty, longer than 16
+	///   characters, or contains a character other than an ASCII letter, digit, or underscore.
+	pub fn new(name: &'a str) -> Result<Self> {
+		if (1..=16).contains(&name.len())
+			&& name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+		{
+			Ok(Self(name))
+		} else {
+			Err(Error::BadParameters)
+		}
+	}
+
+	/// Returns the username as a string slice.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn as_str(&self) -> &'a str {
+		self.0
+	}
+}
+
+impl core::ops::Deref for Username<'_> {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		self.0
+	}
+}
+
+impl Display for Username<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		f.write_str(self.0)
+	}
+}
+
+impl<'a> From<Username<'a>> for &'a str {
+	fn from(value: Username<'a>) -> Self {
+		value.0
+	}
+}
+
+impl<'a> TryFrom<&'a str> for Username<'a> {
+	type Error = Error;
+
+	fn try_from(value: &'a str) -> Result<Self> {
+		Self::new(value)
+	}
+}
+
 /// Grants access to the computer to a user.
 ///
 /// The `name` parameter is the Minecraft username of the user to grant access to.
@@ -192,6 +996,47 @@ pub fn remove_user(name: &str) -> Result<()> {
 	Ok(())
 }
 
+/// Grants access to the computer to a sequence of users.
+///
+/// The `names` parameter is the Minecraft usernames of the users to grant access to. They are
+/// added in order, stopping at the first failure.
+///
+/// # Errors
+/// If adding a user fails, the index of that user within `names` is returned along with the error
+/// from [`add_user`](add_user). The users before that index have been added; the user at that
+/// index and those after it have not.
+pub fn add_users<'a, I: IntoIterator<Item = &'a str>>(
+	names: I,
+) -> core::result::Result<(), (usize, Error)> {
+	for_each_user(names, add_user)
+}
+
+/// Revokes access to the computer from a sequence of users.
+///
+/// The `names` parameter is the Minecraft usernames of the users to revoke access from. They are
+/// removed in order, stopping at the first failure.
+///
+/// # Errors
+/// If removing a user fails, the index of that user within `names` is returned along with the
+/// error from [`remove_user`](remove_user). The users before that index have been removed; the
+/// user at that index and those after it have not.
+pub fn remove_users<'a, I: IntoIterator<Item = &'a str>>(
+	names: I,
+) -> core::result::Result<(), (usize, Error)> {
+	for_each_user(names, remove_user)
+}
+
+/// Applies an ACL operation to each of a sequence of users, stopping at the first failure.
+fn for_each_user<'a>(
+	names: impl IntoIterator<Item = &'a str>,
+	f: fn(&str) -> Result<()>,
+) -> core::result::Result<(), (usize, Error)> {
+	for (index, name) in names.into_iter().enumerate() {
+		f(name).map_err(|e| (index, e))?;
+	}
+	Ok(())
+}
+
 /// Returns the amount of energy stored in the computer and its network.
 #[must_use = "This function is only useful for its return value"]
 pub fn energy() -> NotNan<f64> {
@@ -206,6 +1051,18 @@ pub fn max_energy() -> NotNan<f64> {
 	unsafe { sys::max_energy() }
 }
 
+/// Returns whether the stored energy is below a fraction of the maximum.
+///
+/// The `fraction` parameter is the threshold, as a fraction of [`max_energy`](max_energy); for
+/// example, 0.25 checks whether the computer’s network is less than a quarter full. If the
+/// maximum energy is zero (for example, if the computer is configured not to need power),
+/// `false` is returned.
+#[must_use = "This function is only useful for its return value"]
+pub fn energy_below(fraction: NotNan<f64>) -> bool {
+	let max = max_energy().into_inner();
+	max > 0.0 && energy().into_inner() < fraction.into_inner() * max
+}
+
 /// Returns the width of a Unicode character, in terminal columns.
 ///
 /// The `ch` parameter is the character to examine.
@@ -217,6 +1074,34 @@ pub fn char_width(ch: char) -> u32 {
 	unsafe { sys::char_width(ch) }
 }
 
+/// Returns the width of a string, in terminal columns.
+///
+/// The `s` parameter is the string to examine. The result is the sum of the
+/// [`char_width`](char_width) of each character in the string, saturating at `u32::MAX`.
+#[must_use = "This function is only useful for its return value"]
+pub fn str_width(s: &str) -> u32 {
+	s.chars()
+		.fold(0, |acc, ch| acc.saturating_add(char_width(ch)))
+}
+
+/// Returns how much of a string fits in a number of terminal columns.
+///
+/// The `s` parameter is the string to examine. The `columns` parameter is the number of columns
+/// available. The result is the length, in bytes, of the longest prefix of `s` whose
+/// [`str_width`](str_width) does not exceed `columns`; the prefix ends before the first character
+/// that would overflow, even if later characters are narrower.
+#[must_use = "This function is only useful for its return value"]
+pub fn fit_width(s: &str, columns: u32) -> usize {
+	let mut used = 0_u32;
+	for (index, ch) in s.char_indices() {
+		match used.checked_add(char_width(ch)) {
+			Some(width) if width <= columns => used = width,
+			_ => return index,
+		}
+	}
+	s.len()
+}
+
 /// Plays a beep.
 ///
 /// The `frequency` parameter is the frequency, in Hz, of the beep to play. The `duration`
@@ -240,7 +1125,24 @@ pub fn beep_pattern(pattern: &str) {
 		unsafe{call_string(sys::beep_pattern, Some(pattern))};
 	// Can’t fail because beep_pattern can only fail due to MemoryFault or StringDecode, and
 	// Error::from_i32 already treats those as unreachable.
-	result.unwrap_or_else(|_| panic_or_trap!("unreachable"));
+	result.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e));
+}
+
+/// Plays a series of beeps, after checking that the pattern is valid.
+///
+/// The `pattern` parameter is a Morse code beep pattern to play, consisting only of `.` (short
+/// beep), `-` (long beep), and space characters.
+///
+/// # Errors
+/// * [`BadParameters`](Error::BadParameters) is returned if `pattern` contains any other
+///   character, in which case nothing is played.
+pub fn beep_pattern_checked(pattern: &str) -> Result<()> {
+	if pattern.bytes().all(|b| matches!(b, b'.' | b'-' | b' ')) {
+		beep_pattern(pattern);
+		Ok(())
+	} else {
+		Err(Error::BadParameters)
+	}
 }
 
 /// Shuts down the computer.
@@ -262,11 +1164,94 @@ pub fn error(error: &str) -> ! {
 	unsafe { sys::error(error.as_ptr(), error.len()) }
 }
 
+/// The possible ways of ending execution of the computer.
+///
+/// The `'a` lifetime parameter is the lifetime of the error message, if any.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PowerAction<'a> {
+	/// Shut down the computer, as by [`shutdown`](shutdown).
+	Shutdown,
+
+	/// Reboot the computer, as by [`reboot`](reboot).
+	Reboot,
+
+	/// Halt the computer with an error message, as by [`error`](error).
+	Error(&'a str),
+}
+
+/// Shuts down, reboots, or halts the computer with an error.
+///
+/// The `action` parameter identifies which of these to do.
+pub fn power(action: PowerAction<'_>) -> ! {
+	match action {
+		PowerAction::Shutdown => shutdown(),
+		PowerAction::Reboot => reboot(),
+		PowerAction::Error(message) => error(message),
+	}
+}
+
 /// Sends a message to the debug log, if enabled.
 pub fn debug(message: &str) {
 	// SAFETY: debug permits a string pointer/length pair.
 	let result = unsafe { call_string(sys::debug, Some(message)) };
 	// Can’t fail because beep_pattern can only fail due to MemoryFault or StringDecode, and
 	// Error::from_i32 already treats those as unreachable.
-	result.unwrap_or_else(|_| panic_or_trap!("unreachable"));
+	result.unwrap_or_else(|e| panic_or_trap!("unreachable: {}", e));
+}
+
+#[cfg(all(test, feature = "mock", not(target_arch = "wasm32")))]
+mod tests {
+	use super::*;
+	use crate::mock;
+	use core::task::{RawWaker, RawWakerVTable};
+
+	/// A waker vtable whose clone and drop functions call back into the signal waker slot, as an
+	/// executor’s might.
+	const REENTRANT: RawWakerVTable = RawWakerVTable::new(
+		|data| {
+			wake_signal_waker();
+			RawWaker::new(data, &REENTRANT)
+		},
+		|_| (),
+		|_| (),
+		|_| wake_signal_waker(),
+	);
+
+	fn reentrant_waker(data: usize) -> Waker {
+		// SAFETY: The vtable functions never dereference the data pointer.
+		unsafe { Waker::from_raw(RawWaker::new(data as *const (), &REENTRANT)) }
+	}
+
+	#[test]
+	fn register_signal_waker_reentrant() {
+		let first = reentrant_waker(1);
+		register_signal_waker(&first);
+		let second = reentrant_waker(2);
+		register_signal_waker(&second);
+		wake_signal_waker();
+	}
+
+	#[test]
+	fn try_pull_signal_length_reports_unexpected_code() {
+		mock::reset();
+		mock::push_signal_raw(&[0x61, b'a', 0x80]).unwrap();
+		mock::fail_next("computer::pull_signal", Error::UNKNOWN_CODE);
+		assert_eq!(try_pull_signal_length(), Err(Error::Unknown));
+		assert_eq!(try_pull_signal_length().unwrap().unwrap().get(), 3);
+	}
+
+	#[test]
+	fn fit_width_mixed() {
+		// 'a' and 'b' are one column wide and one byte long; '漢' is two columns wide and three
+		// bytes long.
+		let s = "a漢b";
+		assert_eq!(fit_width(s, 0), 0);
+		assert_eq!(fit_width(s, 1), 1);
+		assert_eq!(fit_width(s, 2), 1);
+		assert_eq!(fit_width(s, 3), 4);
+		assert_eq!(fit_width(s, 4), 5);
+		assert_eq!(fit_width(s, u32::MAX), 5);
+		assert_eq!(fit_width("漢a", 1), 0);
+		assert_eq!(fit_width("", 0), 0);
+	}
 }
@@ -5,17 +5,85 @@
 //! by component calls but cannot be represented as pure data in CBOR.
 
 use super::error::{Error, Result};
+use super::sys::descriptor as sys;
+use crate::panic_or_trap;
+use core::cmp::Ordering;
 use core::fmt::{Debug, Formatter};
 use core::marker::PhantomData;
-use core::mem::forget;
+use core::mem::{forget, size_of, ManuallyDrop};
 use core::num::NonZeroU32;
-use minicbor::data::Tag;
+#[cfg(all(
+	feature = "debug-descriptors",
+	not(all(feature = "mock", not(target_arch = "wasm32")))
+))]
+use core::sync::atomic::{self, AtomicUsize};
+use minicbor::data::{Tag, Type};
 use minicbor::decode::{Decode, Decoder};
 use minicbor::encode::{Encode, Encoder, Write};
-use oc_wasm_sys::descriptor as sys;
 
 /// The Identifier CBOR tag number.
-const IDENTIFIER: Tag = Tag::new(39);
+pub(crate) const IDENTIFIER: Tag = Tag::new(39);
+
+/// The number of [`Owned`](Owned) values currently in existence.
+#[cfg(all(
+	feature = "debug-descriptors",
+	not(all(feature = "mock", not(target_arch = "wasm32")))
+))]
+static OPEN_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(all(
+	feature = "debug-descriptors",
+	feature = "mock",
+	not(target_arch = "wasm32")
+))]
+std::thread_local! {
+	/// The number of [`Owned`](Owned) values currently in existence on this thread.
+	static OPEN_COUNT: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+/// Returns the number of [`Owned`](Owned) descriptors currently in existence.
+///
+/// The count is incremented whenever an `Owned` is created, whether with [`new`](Owned::new) or
+/// from a [`Decoded`](Decoded) with [`into_owned`](Decoded::into_owned), and decremented whenever
+/// one is dropped, closed, or unwrapped with [`into_inner`](Owned::into_inner). A count that keeps
+/// growing over the life of a program indicates a descriptor leak. Descriptors held in other
+/// forms, such as [`Decoded`](Decoded) values that were never converted or raw integers, are not
+/// counted.
+///
+/// When the `mock` feature is in use, the count is kept per thread, like the rest of the simulated
+/// computer.
+#[cfg(feature = "debug-descriptors")]
+#[must_use = "This function is only useful for its return value"]
+pub fn open_count() -> usize {
+	#[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
+	return OPEN_COUNT.load(atomic::Ordering::Relaxed);
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	return OPEN_COUNT.with(core::cell::Cell::get);
+}
+
+/// Records that an [`Owned`](Owned) descriptor has been created.
+#[cfg(feature = "debug-descriptors")]
+fn count_opened() {
+	#[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
+	OPEN_COUNT.fetch_add(1, atomic::Ordering::Relaxed);
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	OPEN_COUNT.with(|count| count.set(count.get() + 1));
+}
+
+/// Records that an [`Owned`](Owned) descriptor has been destroyed.
+#[cfg(feature = "debug-descriptors")]
+fn count_closed() {
+	#[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
+	OPEN_COUNT.fetch_sub(1, atomic::Ordering::Relaxed);
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	OPEN_COUNT.with(|count| count.set(count.get() - 1));
+}
+
+/// The maximum length, in bytes, of the CBOR encoding of a descriptor.
+///
+/// A descriptor is encoded as a two-byte Identifier tag followed by an unsigned integer of up to
+/// five bytes; small descriptors take fewer.
+pub const MAX_CBOR_LEN: usize = 7;
 
 /// CBOR-encodes an opaque value descriptor.
 ///
@@ -28,6 +96,21 @@ fn cbor_encode<W: Write>(
 	Ok(())
 }
 
+/// Duplicates an opaque value descriptor.
+///
+/// The `raw` parameter must be a valid, open descriptor, such as one obtained from an
+/// [`Owned`](Owned) or [`Borrowed`](Borrowed).
+///
+/// # Errors
+/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the descriptor table is too
+///   full and some descriptors must be closed.
+fn dup(raw: u32) -> Result<Owned> {
+	// SAFETY: dup can be invoked with any valid descriptor.
+	let new_desc = Error::from_i32(unsafe { sys::dup(raw) })?;
+	// SAFETY: dup returns a fresh, new descriptor on success.
+	Ok(unsafe { Owned::new(new_desc) })
+}
+
 /// A value that can be converted into an opaque value descriptor.
 ///
 /// A value implementing this trait holds, borrows, or is otherwise able to provide an opaque value
@@ -38,6 +121,18 @@ pub trait AsRaw {
 	fn as_raw(&self) -> u32;
 }
 
+impl<T: AsRaw + ?Sized> AsRaw for &T {
+	fn as_raw(&self) -> u32 {
+		(**self).as_raw()
+	}
+}
+
+impl<T: AsRaw + ?Sized> AsRaw for &mut T {
+	fn as_raw(&self) -> u32 {
+		(**self).as_raw()
+	}
+}
+
 /// A value that can be borrowed as an opaque value descriptor.
 ///
 /// A value implementing this trait is able to produce a [`Borrowed`](Borrowed) value referring to
@@ -49,6 +144,18 @@ pub trait AsDescriptor {
 	fn as_descriptor(&self) -> Borrowed<'_>;
 }
 
+impl<T: AsDescriptor + ?Sized> AsDescriptor for &T {
+	fn as_descriptor(&self) -> Borrowed<'_> {
+		(**self).as_descriptor()
+	}
+}
+
+impl<T: AsDescriptor + ?Sized> AsDescriptor for &mut T {
+	fn as_descriptor(&self) -> Borrowed<'_> {
+		(**self).as_descriptor()
+	}
+}
+
 /// A value that can be converted into an opaque value descriptor.
 ///
 /// A value implementing this trait is able to produce an [`Owned`](Owned) value referring to a
@@ -60,14 +167,74 @@ pub trait IntoDescriptor {
 	fn into_descriptor(self) -> Owned;
 }
 
+/// A value that can be converted into an opaque value descriptor, provided the caller vouches for
+/// its validity.
+///
+/// This is the unchecked counterpart of [`IntoDescriptor`](IntoDescriptor). It is implemented for
+/// every type that implements `IntoDescriptor`, for which the conversion is always sound, and
+/// also for [`Decoded`](Decoded), for which it is not. Generic code that needs to accept both
+/// kinds of value can be written against this trait, leaving the caller to justify the unsafe
+/// call.
+#[allow(clippy::module_name_repetitions)] // Parallels IntoDescriptor.
+pub trait IntoDescriptorUnchecked {
+	/// Converts to the descriptor.
+	///
+	/// # Safety
+	/// The caller must ensure that the descriptor held by `self` is valid and that no other value
+	/// claims ownership of it. For a [`Decoded`](Decoded), this means it must have been decoded
+	/// from the result of a method call, exactly once; see
+	/// [`Decoded::into_owned`](Decoded::into_owned) for details. For types implementing
+	/// [`IntoDescriptor`](IntoDescriptor), this is always the case.
+	#[must_use = "This function is only useful for its return value"]
+	unsafe fn into_descriptor_unchecked(self) -> Owned;
+}
+
+impl<T: IntoDescriptor> IntoDescriptorUnchecked for T {
+	unsafe fn into_descriptor_unchecked(self) -> Owned {
+		self.into_descriptor()
+	}
+}
+
+/// A value that can be duplicated, where duplication may fail.
+///
+/// This is the fallible counterpart of `Clone`. It is implemented for every `Clone` type, for which
+/// duplication always succeeds, and for [`Owned`](Owned), for which duplicating the descriptor can
+/// fail if the descriptor table is full. Generic code that needs to duplicate values can be
+/// written against this trait to accept both.
+pub trait TryClone: Sized {
+	/// Duplicates the value.
+	///
+	/// # Errors
+	/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the value holds a
+	///   descriptor and the descriptor table is too full.
+	fn try_clone(&self) -> Result<Self>;
+}
+
+impl<T: Clone> TryClone for T {
+	fn try_clone(&self) -> Result<Self> {
+		Ok(self.clone())
+	}
+}
+
 /// An owned opaque value descriptor.
 ///
 /// A value of this type encapsulates an opaque value descriptor. Cloning it duplicates the
-/// descriptor. Dropping it closes the descriptor. CBOR-encoding it yields an integer with the
-/// Identifier tag.
+/// descriptor. Dropping it closes the descriptor, unless the `manual-descriptors` feature is
+/// enabled, in which case dropping it leaks the descriptor and [`close`](Owned::close) must be
+/// called instead. CBOR-encoding it yields an integer with the Identifier tag.
+///
+/// Internally, the descriptor is stored as a [`NonZeroU32`](NonZeroU32) holding the raw descriptor
+/// value plus one, and the type is `repr(transparent)`. Therefore `Option<Owned>` is guaranteed to
+/// be the same size as `u32`, with `None` represented as zero. The internal representation can be
+/// obtained and restored via [`as_non_zero`](Owned::as_non_zero) and
+/// [`from_non_zero`](Owned::from_non_zero), for example to pass an `Option<Owned>` across an FFI
+/// boundary.
 #[derive(Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[repr(transparent)]
 pub struct Owned(NonZeroU32);
 
+const _: () = assert!(size_of::<Option<Owned>>() == size_of::<u32>());
+
 impl Owned {
 	/// Wraps a raw integer descriptor in a `Descriptor` object.
 	///
@@ -80,7 +247,9 @@ impl Owned {
 	/// The caller must ensure that only one `Descriptor` object for a given value exists at a
 	/// time, because dropping a `Descriptor` object closes the descriptor.
 	#[allow(clippy::must_use_candidate)] // This could be called and immediately dropped to close an unwanted descriptor.
-	pub const unsafe fn new(raw: u32) -> Self {
+	pub unsafe fn new(raw: u32) -> Self {
+		#[cfg(feature = "debug-descriptors")]
+		count_opened();
 		// SAFETY: The caller is required to pass a valid descriptor. Any valid descriptor is a
 		// small nonnegative integer. Therefore, any descriptor plus one is a small positive
 		// integer.
@@ -93,8 +262,12 @@ impl Owned {
 	/// because Rust’s safety guarantees to not include reliable freeing of resources; however,
 	/// care should be taken when calling it.
 	#[must_use = "The returned descriptor will leak if not manually closed"]
-	pub const fn into_inner(self) -> u32 {
+	pub fn into_inner(self) -> u32 {
+		#[cfg(feature = "debug-descriptors")]
+		count_closed();
 		let ret = self.as_raw();
+		// Owned does not implement Drop when the manual-descriptors feature is enabled.
+		#[allow(clippy::forget_non_drop)]
 		forget(self);
 		ret
 	}
@@ -105,16 +278,105 @@ impl Owned {
 		self.0.get() - 1
 	}
 
+	/// Returns the internal representation of the descriptor, which is the raw descriptor value
+	/// plus one.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn as_non_zero(&self) -> NonZeroU32 {
+		self.0
+	}
+
+	/// Wraps the internal representation of a descriptor, as returned by
+	/// [`as_non_zero`](Owned::as_non_zero), in a `Descriptor` object.
+	///
+	/// # Safety
+	/// The caller must ensure that `value` minus one is a valid, open descriptor, and that only
+	/// one `Descriptor` object for it exists at a time, as for [`new`](Owned::new).
+	#[allow(clippy::must_use_candidate)] // This could be called and immediately dropped to close an unwanted descriptor.
+	pub unsafe fn from_non_zero(value: NonZeroU32) -> Self {
+		Self::new(value.get() - 1)
+	}
+
+	/// Borrows the descriptor.
+	///
+	/// This is the same as [`as_descriptor`](AsDescriptor::as_descriptor), but is an inherent
+	/// method, so it can be called without importing [`AsDescriptor`](AsDescriptor) and makes the
+	/// lifetime of the resulting borrow explicit.
+	///
+	/// # Example
+	/// ```no_run
+	/// use oc_wasm_safe::descriptor::{Borrowed, Owned};
+	///
+	/// /// Returns whichever descriptor has the lower raw value.
+	/// fn lower<'a>(x: Borrowed<'a>, y: Borrowed<'a>) -> Borrowed<'a> {
+	///     if x.as_raw() <= y.as_raw() {
+	///         x
+	///     } else {
+	///         y
+	///     }
+	/// }
+	///
+	/// fn lowest_raw(first: &Owned, second: Borrowed<'_>) -> u32 {
+	///     lower(first.borrow(), second.reborrow()).as_raw()
+	/// }
+	/// ```
+	///
+	/// The borrow keeps the `Owned` alive, so it cannot be closed while the borrow is in use:
+	/// ```compile_fail,E0505
+	/// use oc_wasm_safe::descriptor::{Borrowed, Owned};
+	///
+	/// fn keep<'a>(descriptor: Borrowed<'a>) -> Borrowed<'a> {
+	///     descriptor
+	/// }
+	///
+	/// // SAFETY: Never run; this only needs to fail to compile.
+	/// let owned = unsafe { Owned::new(0) };
+	/// let borrowed = keep(owned.borrow());
+	/// owned.close();
+	/// let _ = borrowed.as_raw();
+	/// ```
+	#[must_use = "This function is only useful for its return value"]
+	pub fn borrow<'a>(&'a self) -> Borrowed<'a> {
+		Borrowed(self.0, PhantomData)
+	}
+
+	/// Duplicates the descriptor.
+	///
+	/// This is the equivalent of `Clone::clone`, which `Owned` cannot implement because
+	/// duplicating a descriptor can fail.
+	///
+	/// # Errors
+	/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the descriptor table is
+	///   too full and some descriptors must be closed.
+	pub fn try_clone(&self) -> Result<Self> {
+		dup(self.as_raw())
+	}
+
 	/// Duplicates the descriptor.
 	///
 	/// # Errors
 	/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the descriptor table is
 	///   too full and some descriptors must be closed.
+	#[deprecated(note = "use try_clone instead")]
 	pub fn dup(&self) -> Result<Self> {
-		// SAFETY: dup can be invoked with any valid descriptor.
-		let new_desc = Error::from_i32(unsafe { sys::dup(self.as_raw()) })?;
-		// SAFETY: dup returns a fresh, new descriptor on success.
-		Ok(unsafe { Self::new(new_desc) })
+		self.try_clone()
+	}
+
+	/// Closes the descriptor.
+	///
+	/// This is equivalent to dropping the `Owned` object, except that it also closes the
+	/// descriptor when the `manual-descriptors` feature is enabled.
+	pub fn close(self) {
+		let raw = self.into_inner();
+		// SAFETY: The contained descriptor is always valid. There can be only one Owned object in
+		// existence for a given open descriptor, and into_inner has consumed it. Therefore, the
+		// descriptor is valid and closing it will not break any other objects.
+		unsafe { sys::close(raw) };
+	}
+}
+
+impl TryClone for Owned {
+	fn try_clone(&self) -> Result<Self> {
+		dup(self.as_raw())
 	}
 }
 
@@ -142,13 +404,16 @@ impl Debug for Owned {
 	}
 }
 
+#[cfg(not(feature = "manual-descriptors"))]
 impl Drop for Owned {
 	fn drop(&mut self) {
 		// SAFETY: The contained descriptor is always valid. There can be only one Owned object in
-		// existence for a given open descriptor. There is no safe way to close a descriptor other
-		// than dropping the Owned object. Therefore, the descriptor is valid and closing it will
-		// not break any other objects.
+		// existence for a given open descriptor. The only safe ways to close a descriptor are
+		// dropping the Owned object and calling close, which consumes it. Therefore, the
+		// descriptor is valid and closing it will not break any other objects.
 		unsafe { sys::close(self.as_raw()) };
+		#[cfg(feature = "debug-descriptors")]
+		count_closed();
 	}
 }
 
@@ -162,6 +427,49 @@ impl<Context> Encode<Context> for Owned {
 	}
 }
 
+/// Closes a collection of descriptors.
+///
+/// Each descriptor is closed with [`Owned::close`](Owned::close), so this works even when the
+/// `manual-descriptors` feature is enabled. The number of descriptors closed is returned.
+#[allow(clippy::must_use_candidate)] // The count is often not needed.
+pub fn close_all<I: IntoIterator<Item = Owned>>(descriptors: I) -> usize {
+	let mut count = 0;
+	for descriptor in descriptors {
+		descriptor.close();
+		count += 1;
+	}
+	count
+}
+
+/// Checks whether a number of additional descriptors can be opened.
+///
+/// OC-Wasm does not document a fixed limit on the number of open descriptors, so there is no
+/// constant to compare the number of open descriptors against. Instead, this function duplicates
+/// `descriptor` `N` times, holding all of the duplicates open at once, and then closes them all
+/// again. If that succeeds, `N` more descriptors can be opened, until something else opens a
+/// descriptor.
+///
+/// The duplicates are held in an array on the stack, so `N` should be kept reasonably small.
+///
+/// # Errors
+/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the descriptor table would
+///   not have room for `N` more descriptors.
+pub fn try_reserve<const N: usize>(descriptor: &impl AsDescriptor) -> Result<()> {
+	let mut duplicates: [Option<Owned>; N] = core::array::from_fn(|_| None);
+	let mut result = Ok(());
+	for slot in &mut duplicates {
+		match dup(descriptor.as_descriptor().as_raw()) {
+			Ok(duplicate) => *slot = Some(duplicate),
+			Err(e) => {
+				result = Err(e);
+				break;
+			}
+		}
+	}
+	close_all(duplicates.into_iter().flatten());
+	result
+}
+
 /// A borrowed opaque value descriptor.
 ///
 /// A value of this type encapsulates an opaque value descriptor. Copying or cloning it produces a
@@ -177,6 +485,26 @@ impl Borrowed<'_> {
 	pub const fn as_raw(self) -> u32 {
 		self.0.get() - 1
 	}
+
+	/// Reborrows the descriptor for a possibly shorter lifetime.
+	///
+	/// The returned value borrows from `self` rather than from the original [`Owned`](Owned)
+	/// value. This is useful when passing the descriptor to a function whose signature requires a
+	/// borrow no longer than that of some other reference.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn reborrow(&self) -> Borrowed<'_> {
+		Borrowed(self.0, PhantomData)
+	}
+
+	/// Duplicates the descriptor, producing an [`Owned`](Owned) descriptor that can outlive the
+	/// borrow.
+	///
+	/// # Errors
+	/// * [`TooManyDescriptors`](Error::TooManyDescriptors) is returned if the descriptor table is
+	///   too full and some descriptors must be closed.
+	pub fn to_owned(self) -> Result<Owned> {
+		dup(self.as_raw())
+	}
 }
 
 impl AsRaw for Borrowed<'_> {
@@ -207,6 +535,91 @@ impl<Context> Encode<Context> for Borrowed<'_> {
 	}
 }
 
+// Comparisons between owned and borrowed descriptors, and between descriptors and raw values,
+// compare the raw descriptor values. They are comparison-only and never create or close anything.
+
+impl PartialEq<Borrowed<'_>> for Owned {
+	fn eq(&self, other: &Borrowed<'_>) -> bool {
+		self.0 == other.0
+	}
+}
+
+impl PartialEq<Owned> for Borrowed<'_> {
+	fn eq(&self, other: &Owned) -> bool {
+		self.0 == other.0
+	}
+}
+
+impl PartialOrd<Borrowed<'_>> for Owned {
+	fn partial_cmp(&self, other: &Borrowed<'_>) -> Option<Ordering> {
+		self.0.partial_cmp(&other.0)
+	}
+}
+
+impl PartialOrd<Owned> for Borrowed<'_> {
+	fn partial_cmp(&self, other: &Owned) -> Option<Ordering> {
+		self.0.partial_cmp(&other.0)
+	}
+}
+
+impl PartialEq<u32> for Owned {
+	fn eq(&self, other: &u32) -> bool {
+		self.as_raw() == *other
+	}
+}
+
+impl PartialEq<u32> for Borrowed<'_> {
+	fn eq(&self, other: &u32) -> bool {
+		self.as_raw() == *other
+	}
+}
+
+/// CBOR-encodes a sequence of opaque value descriptors.
+///
+/// Each descriptor is written as an integer with the Identifier tag, exactly as the
+/// [`Encode`](Encode) implementations of [`Owned`](Owned) and [`Borrowed`](Borrowed) would write
+/// it. No array header is written, so the caller can mix the descriptors with other parameters;
+/// see [`encode_array`](encode_array) to write them as an array on their own.
+///
+/// The descriptors are taken by reference, so encoding them never consumes, and therefore never
+/// closes, an [`Owned`](Owned).
+///
+/// # Errors
+/// Any error returned by the encoder’s writer is returned by this function.
+pub fn encode_all<'descriptor, W: Write, I, D>(
+	e: &mut Encoder<W>,
+	descriptors: I,
+) -> core::result::Result<(), minicbor::encode::Error<W::Error>>
+where
+	I: IntoIterator<Item = &'descriptor D>,
+	D: AsDescriptor + ?Sized + 'descriptor,
+{
+	for descriptor in descriptors {
+		cbor_encode(descriptor.as_descriptor().as_raw(), e)?;
+	}
+	Ok(())
+}
+
+/// CBOR-encodes a sequence of opaque value descriptors as an array.
+///
+/// This writes an array header followed by the descriptors, as [`encode_all`](encode_all) does.
+///
+/// # Errors
+/// Any error returned by the encoder’s writer is returned by this function.
+pub fn encode_array<'descriptor, W: Write, I, D>(
+	e: &mut Encoder<W>,
+	descriptors: I,
+) -> core::result::Result<(), minicbor::encode::Error<W::Error>>
+where
+	I: IntoIterator<Item = &'descriptor D>,
+	I::IntoIter: ExactSizeIterator,
+	D: AsDescriptor + ?Sized + 'descriptor,
+{
+	let descriptors = descriptors.into_iter();
+	e.array(descriptors.len() as u64)?;
+	encode_all(e, descriptors)
+}
+
 /// A CBOR-decoded opaque value descriptor.
 ///
 /// A value of this type encapsulates an opaque value descriptor. It cannot be cloned. Dropping it
@@ -239,8 +652,21 @@ impl Decoded {
 	/// [`Owned`](Owned) type via this `unsafe` method.
 	#[allow(clippy::must_use_candidate)] // If caller doesn’t want the descriptor, they can do this and immediately drop.
 	pub unsafe fn into_owned(self) -> Owned {
+		#[cfg(feature = "debug-descriptors")]
+		count_opened();
 		Owned(self.0)
 	}
+
+	/// Returns the raw descriptor value.
+	pub(crate) const fn as_raw(&self) -> u32 {
+		self.0.get() - 1
+	}
+}
+
+impl IntoDescriptorUnchecked for Decoded {
+	unsafe fn into_descriptor_unchecked(self) -> Owned {
+		self.into_owned()
+	}
 }
 
 impl Debug for Decoded {
@@ -258,6 +684,393 @@ impl<'b, Context> Decode<'b, Context> for Decoded {
 		if tag != IDENTIFIER {
 			return Err(minicbor::decode::Error::message("expected Identifier tag"));
 		}
-		Ok(Self(NonZeroU32::new(d.u32()? + 1).unwrap()))
+		let position = d.position();
+		let raw = d
+			.u32()?
+			.checked_add(1)
+			.and_then(NonZeroU32::new)
+			.ok_or_else(|| {
+				minicbor::decode::Error::message("descriptor value out of range").at(position)
+			})?;
+		Ok(Self(raw))
+	}
+}
+
+/// A CBOR decoding helper that requires the next data item to be an opaque value descriptor.
+///
+/// Decoding a value of this type succeeds only if the next data item is an unsigned integer with
+/// the Identifier tag, yielding the contained [`Decoded`](Decoded) descriptor. If the next data
+/// item is anything else, decoding fails with an error message describing what was found instead.
+/// This is useful when decoding a method call result in which a particular position is known to
+/// hold a descriptor.
+#[derive(Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ExpectDescriptor(Decoded);
+
+impl ExpectDescriptor {
+	/// Returns the decoded descriptor.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn into_inner(self) -> Decoded {
+		self.0
+	}
+}
+
+impl From<ExpectDescriptor> for Decoded {
+	fn from(source: ExpectDescriptor) -> Self {
+		source.into_inner()
+	}
+}
+
+impl<'b, Context> Decode<'b, Context> for ExpectDescriptor {
+	fn decode(
+		d: &mut Decoder<'b>,
+		_: &mut Context,
+	) -> core::result::Result<Self, minicbor::decode::Error> {
+		let position = d.position();
+		let datatype = d.datatype()?;
+		if datatype != Type::Tag {
+			return Err(minicbor::decode::Error::type_mismatch(datatype)
+				.with_message("expected descriptor (Identifier-tagged integer)")
+				.at(position));
+		}
+		if d.tag()? != IDENTIFIER {
+			return Err(minicbor::decode::Error::message(
+				"expected descriptor but found a tag other than Identifier",
+			)
+			.at(position));
+		}
+		let position = d.position();
+		let datatype = d.datatype()?;
+		if !matches!(datatype, Type::U8 | Type::U16 | Type::U32) {
+			return Err(minicbor::decode::Error::type_mismatch(datatype)
+				.with_message("expected descriptor but Identifier tag does not enclose a u32")
+				.at(position));
+		}
+		let raw = d
+			.u32()?
+			.checked_add(1)
+			.and_then(NonZeroU32::new)
+			.ok_or_else(|| {
+				minicbor::decode::Error::message("descriptor value out of range").at(position)
+			})?;
+		Ok(Self(Decoded(raw)))
+	}
+}
+
+/// A handle identifying a descriptor stored in a [`Table`](Table).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Handle(usize);
+
+/// A fixed-capacity table of owned descriptors.
+///
+/// A value of this type stores up to `N` [`Owned`](Owned) descriptors, each identified by a small
+/// [`Handle`](Handle). It is intended for applications written as state machines which need to
+/// keep many descriptors (such as open file handles) in a `static` variable. Dropping the table
+/// drops all the descriptors it contains.
+///
+/// A handle becomes invalid when its descriptor is removed from the table. The slot may then be
+/// reused by a later insertion, so the caller must take care not to use a handle after removing
+/// its descriptor.
+#[derive(Debug)]
+pub struct Table<const N: usize>([Option<Owned>; N]);
+
+impl<const N: usize> Table<N> {
+	/// Creates an empty table.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn new() -> Self {
+		const EMPTY: Option<Owned> = None;
+		Self([EMPTY; N])
+	}
+
+	/// Adds a descriptor to the table.
+	///
+	/// On success, the handle identifying the descriptor is returned.
+	///
+	/// # Errors
+	/// If the table already contains `N` descriptors, `descriptor` is returned so that the caller
+	/// can store it elsewhere or close it.
+	pub fn insert(&mut self, descriptor: Owned) -> core::result::Result<Handle, Owned> {
+		match self.0.iter_mut().position(|slot| slot.is_none()) {
+			Some(index) => {
+				self.0[index] = Some(descriptor);
+				Ok(Handle(index))
+			}
+			None => Err(descriptor),
+		}
+	}
+
+	/// Borrows a descriptor from the table.
+	///
+	/// If `handle` does not refer to a descriptor in the table, `None` is returned.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn get(&self, handle: Handle) -> Option<Borrowed<'_>> {
+		self.0
+			.get(handle.0)
+			.and_then(Option::as_ref)
+			.map(Owned::as_descriptor)
+	}
+
+	/// Removes a descriptor from the table.
+	///
+	/// If `handle` refers to a descriptor in the table, the descriptor is removed and returned. If
+	/// not, `None` is returned.
+	#[allow(clippy::must_use_candidate)] // This could be called and immediately dropped to close the descriptor.
+	pub fn remove(&mut self, handle: Handle) -> Option<Owned> {
+		self.0.get_mut(handle.0).and_then(Option::take)
+	}
+
+	/// Closes all the descriptors in the table, leaving it empty.
+	pub fn close_all(&mut self) {
+		for descriptor in self.0.iter_mut().filter_map(Option::take) {
+			descriptor.close();
+		}
+	}
+}
+
+impl<const N: usize> Default for Table<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// An owned opaque value descriptor that must be closed explicitly.
+///
+/// A value of this type holds an [`Owned`](Owned) descriptor but, unlike `Owned`, does not close
+/// the descriptor when dropped. Instead, the descriptor must be released by calling
+/// [`close`](ManuallyClose::close), or converted back into an automatically closing `Owned` by
+/// calling [`into_inner`](ManuallyClose::into_inner). Dropping a `ManuallyClose` leaks the
+/// descriptor; in debug builds, it also panics or traps (depending on the `panic` feature) so that
+/// the leak is noticed.
+#[derive(Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[must_use = "A ManuallyClose leaks its descriptor if dropped. It must be closed with close() or converted with into_inner()."]
+pub struct ManuallyClose(ManuallyDrop<Owned>);
+
+impl ManuallyClose {
+	/// Wraps an owned descriptor so that it must be closed explicitly.
+	pub const fn new(descriptor: Owned) -> Self {
+		Self(ManuallyDrop::new(descriptor))
+	}
+
+	/// Returns the wrapped descriptor, which will once again be closed automatically when dropped.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn into_inner(self) -> Owned {
+		let mut this = ManuallyDrop::new(self);
+		// SAFETY: this is never used again, and because it is wrapped in ManuallyDrop, its Drop
+		// implementation will not run.
+		unsafe { ManuallyDrop::take(&mut this.0) }
+	}
+
+	/// Closes the descriptor.
+	///
+	/// # Errors
+	/// * [`BadDescriptor`](Error::BadDescriptor) is returned if the descriptor is not open. This
+	///   should be impossible, because the descriptor came from an [`Owned`](Owned).
+	pub fn close(self) -> Result<()> {
+		let raw = self.into_inner().into_inner();
+		// SAFETY: The descriptor came from an Owned, so it is valid, and into_inner has consumed
+		// that Owned, so no other object refers to it.
+		Error::from_i32(unsafe { sys::close(raw) })?;
+		Ok(())
+	}
+}
+
+impl AsRaw for ManuallyClose {
+	fn as_raw(&self) -> u32 {
+		self.0.as_raw()
+	}
+}
+
+impl AsDescriptor for ManuallyClose {
+	fn as_descriptor(&self) -> Borrowed<'_> {
+		self.0.as_descriptor()
+	}
+}
+
+impl Drop for ManuallyClose {
+	fn drop(&mut self) {
+		#[cfg(debug_assertions)]
+		{
+			// A ManuallyClose dropped during unwinding is not a bug in its own right, and panicking
+			// again would abort the process and hide the original panic.
+			#[cfg(feature = "std")]
+			if std::thread::panicking() {
+				return;
+			}
+			panic_or_trap!(
+				"ManuallyClose dropped without being closed (descriptor {})",
+				self.as_raw()
+			);
+		}
+	}
+}
+
+impl<Context> Encode<Context> for ManuallyClose {
+	fn encode<W: Write>(
+		&self,
+		e: &mut Encoder<W>,
+		_: &mut Context,
+	) -> core::result::Result<(), minicbor::encode::Error<W::Error>> {
+		cbor_encode(self.as_raw(), e)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decoded_rejects_max_u32() {
+		// 39(4294967295): adding one for the niche would overflow.
+		let buffer = [0xD8, 0x27, 0x1A, 0xFF, 0xFF, 0xFF, 0xFF];
+		assert!(minicbor::decode::<Decoded>(&buffer).is_err());
+	}
+
+	#[test]
+	fn decoded_accepts_largest_valid() {
+		// 39(4294967294)
+		let buffer = [0xD8, 0x27, 0x1A, 0xFF, 0xFF, 0xFF, 0xFE];
+		let decoded = minicbor::decode::<Decoded>(&buffer).unwrap();
+		assert_eq!(decoded.0.get(), u32::MAX);
+	}
+
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	#[test]
+	fn borrow_through_generic_function() {
+		fn lower<'a>(x: Borrowed<'a>, y: Borrowed<'a>) -> Borrowed<'a> {
+			if x.as_raw() <= y.as_raw() {
+				x
+			} else {
+				y
+			}
+		}
+
+		crate::mock::reset();
+		// SAFETY: The descriptors were just opened and nothing else owns them.
+		let first = unsafe { Owned::new(crate::mock::new_descriptor()) };
+		// SAFETY: As above.
+		let second = unsafe { Owned::new(crate::mock::new_descriptor()) };
+		let borrowed = second.borrow();
+		assert_eq!(borrowed.reborrow().as_raw(), second.as_raw());
+		assert_eq!(
+			lower(first.borrow(), borrowed.reborrow()).as_raw(),
+			first.as_raw()
+		);
+		assert_eq!(lower(borrowed, borrowed).as_raw(), second.as_raw());
+		first.close();
+		second.close();
+	}
+
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	#[test]
+	fn try_reserve_limit() {
+		crate::mock::reset();
+		crate::mock::computer(|c| c.descriptor_limit = 4);
+		// SAFETY: The descriptor was just opened and nothing else owns it.
+		let owned = unsafe { Owned::new(crate::mock::new_descriptor()) };
+		assert_eq!(try_reserve::<0>(&owned), Ok(()));
+		assert_eq!(try_reserve::<3>(&owned), Ok(()));
+		assert_eq!(crate::mock::open_descriptors(), 1);
+		assert_eq!(try_reserve::<4>(&owned), Err(Error::TooManyDescriptors));
+		assert_eq!(crate::mock::open_descriptors(), 1);
+		owned.close();
+	}
+
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	#[test]
+	fn encode_array_borrows() {
+		crate::mock::reset();
+		// SAFETY: The descriptors were just opened and nothing else owns them.
+		let first = unsafe { Owned::new(crate::mock::new_descriptor()) };
+		// SAFETY: As above.
+		let second = unsafe { Owned::new(crate::mock::new_descriptor()) };
+		let mut buffer = [0_u8; 7];
+		let mut encoder = Encoder::new(&mut buffer[..]);
+		let descriptors = [first, second];
+		encode_array(&mut encoder, &descriptors).unwrap();
+		// [39(0), 39(1)]
+		assert_eq!(buffer, [0x82, 0xD8, 0x27, 0x00, 0xD8, 0x27, 0x01]);
+		assert_eq!(crate::mock::open_descriptors(), 2);
+		for descriptor in descriptors {
+			descriptor.close();
+		}
+	}
+
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	#[test]
+	fn manually_close_close() {
+		crate::mock::reset();
+		// SAFETY: The descriptor was just opened and nothing else owns it.
+		let descriptor = ManuallyClose::new(unsafe { Owned::new(crate::mock::new_descriptor()) });
+		assert_eq!(descriptor.close(), Ok(()));
+		assert_eq!(crate::mock::open_descriptors(), 0);
+	}
+
+	#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+	#[test]
+	fn manually_close_into_inner() {
+		crate::mock::reset();
+		let raw = crate::mock::new_descriptor();
+		// SAFETY: The descriptor was just opened and nothing else owns it.
+		let descriptor = ManuallyClose::new(unsafe { Owned::new(raw) });
+		let owned = descriptor.into_inner();
+		assert_eq!(owned.as_raw(), raw);
+		assert_eq!(crate::mock::open_descriptors(), 1);
+		owned.close();
+		assert_eq!(crate::mock::open_descriptors(), 0);
+	}
+
+	#[cfg(all(
+		debug_assertions,
+		feature = "mock",
+		feature = "panic",
+		not(target_arch = "wasm32")
+	))]
+	#[test]
+	#[should_panic(expected = "ManuallyClose dropped without being closed")]
+	fn manually_close_drop_panics() {
+		crate::mock::reset();
+		// SAFETY: The descriptor was just opened and nothing else owns it.
+		drop(ManuallyClose::new(unsafe {
+			Owned::new(crate::mock::new_descriptor())
+		}));
+	}
+
+	#[cfg(all(
+		debug_assertions,
+		feature = "mock",
+		feature = "panic",
+		not(target_arch = "wasm32")
+	))]
+	#[test]
+	#[should_panic(expected = "original panic")]
+	fn manually_close_drop_while_panicking() {
+		crate::mock::reset();
+		// SAFETY: The descriptor was just opened and nothing else owns it.
+		let _descriptor = ManuallyClose::new(unsafe { Owned::new(crate::mock::new_descriptor()) });
+		panic!("original panic");
+	}
+
+	#[cfg(all(
+		feature = "debug-descriptors",
+		feature = "mock",
+		not(feature = "manual-descriptors"),
+		not(target_arch = "wasm32")
+	))]
+	#[test]
+	fn open_count_tracks_owned() {
+		crate::mock::reset();
+		let before = open_count();
+		// SAFETY: The descriptor was just opened and nothing else owns it.
+		let first = unsafe { Owned::new(crate::mock::new_descriptor()) };
+		assert_eq!(open_count(), before + 1);
+		// SAFETY: As above.
+		let second = unsafe { Owned::new(crate::mock::new_descriptor()) };
+		assert_eq!(open_count(), before + 2);
+		let raw = second.into_inner();
+		assert_eq!(open_count(), before + 1);
+		drop(first);
+		assert_eq!(open_count(), before);
+		// SAFETY: into_inner gave up ownership of the descriptor, so this is the only owner.
+		unsafe { Owned::new(raw) }.close();
+		assert_eq!(open_count(), before);
 	}
 }
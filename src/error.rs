@@ -1,8 +1,13 @@
 use crate::panic_or_trap;
 use core::fmt::{Display, Formatter};
 
-/// The errors that a system call can return.
+/// The errors that a system call, or OC-Wasm-Safe itself, can return.
+///
+/// Most variants correspond to error codes returned by OC-Wasm system calls. The exception is
+/// [`DuplicateDescriptor`](Error::DuplicateDescriptor), which OC-Wasm-Safe detects on its own when
+/// taking ownership of descriptors.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
 	/// A CBOR data item is invalid CBOR or encodes an unsupported type or value.
 	CborDecode,
@@ -34,6 +39,12 @@ pub enum Error {
 	/// The operation failed for an otherwise unspecified reason.
 	Other,
 
+	/// A method call result contains the same descriptor more than once.
+	///
+	/// This error is never returned by a system call. It is detected by OC-Wasm-Safe when taking
+	/// ownership of descriptors, to avoid creating two owners of the same descriptor.
+	DuplicateDescriptor,
+
 	/// A system call returned an error code that does not correspond to any known value.
 	///
 	/// It is likely that OC-Wasm has been updated to a version which adds new error codes, and
@@ -56,17 +67,89 @@ impl Error {
 			Self::BadDescriptor => "Bad descriptor",
 			Self::TooManyDescriptors => "Too many descriptors",
 			Self::Other => "Other error",
+			Self::DuplicateDescriptor => "Duplicate descriptor",
 			Self::Unknown => "Unknown error",
 		}
 	}
 }
 
+impl Error {
+	/// The code returned by [`code`](Error::code) for [`Unknown`](Error::Unknown).
+	///
+	/// This value is not used by OC-Wasm for any error.
+	pub const UNKNOWN_CODE: i32 = i32::MIN;
+
+	/// The code returned by [`code`](Error::code) for
+	/// [`DuplicateDescriptor`](Error::DuplicateDescriptor).
+	///
+	/// This value is not used by OC-Wasm for any error.
+	pub const DUPLICATE_DESCRIPTOR_CODE: i32 = i32::MIN + 1;
+
+	/// Returns the numeric system call error code for the error.
+	///
+	/// The code is negative, as returned by a system call. For [`Unknown`](Error::Unknown), which
+	/// does not correspond to any single code, [`UNKNOWN_CODE`](Error::UNKNOWN_CODE) is returned.
+	/// For [`DuplicateDescriptor`](Error::DuplicateDescriptor), which is never returned by a system
+	/// call, [`DUPLICATE_DESCRIPTOR_CODE`](Error::DUPLICATE_DESCRIPTOR_CODE) is returned.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn code(self) -> i32 {
+		match self {
+			Self::CborDecode => -2,
+			Self::BufferTooShort => -4,
+			Self::NoSuchComponent => -5,
+			Self::NoSuchMethod => -6,
+			Self::BadParameters => -7,
+			Self::QueueFull => -8,
+			Self::QueueEmpty => -9,
+			Self::BadDescriptor => -10,
+			Self::TooManyDescriptors => -11,
+			Self::Other => -12,
+			Self::DuplicateDescriptor => Self::DUPLICATE_DESCRIPTOR_CODE,
+			Self::Unknown => Self::UNKNOWN_CODE,
+		}
+	}
+
+	/// Returns the error corresponding to a numeric system call error code.
+	///
+	/// This is the inverse of [`code`](Error::code) for errors that a system call can return. Any
+	/// other value, including the codes for memory faults and string decode errors (which cannot be
+	/// represented by this type), nonnegative values, and
+	/// [`DUPLICATE_DESCRIPTOR_CODE`](Error::DUPLICATE_DESCRIPTOR_CODE) (which no system call
+	/// returns), maps to [`Unknown`](Error::Unknown).
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn from_code(code: i32) -> Self {
+		match code {
+			-2 => Self::CborDecode,
+			-4 => Self::BufferTooShort,
+			-5 => Self::NoSuchComponent,
+			-6 => Self::NoSuchMethod,
+			-7 => Self::BadParameters,
+			-8 => Self::QueueFull,
+			-9 => Self::QueueEmpty,
+			-10 => Self::BadDescriptor,
+			-11 => Self::TooManyDescriptors,
+			-12 => Self::Other,
+			_ => Self::Unknown,
+		}
+	}
+}
+
 impl Display for Error {
 	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
 		f.write_str(self.as_str())
 	}
 }
 
+impl From<minicbor::decode::Error> for Error {
+	/// Converts a CBOR decode error into [`CborDecode`](Error::CborDecode).
+	///
+	/// This allows the `?` operator to be used uniformly in functions that both decode CBOR and
+	/// make system calls. The details of the decode error are discarded.
+	fn from(_: minicbor::decode::Error) -> Self {
+		Self::CborDecode
+	}
+}
+
 impl Error {
 	/// Checks a system call return value of type `isize` for an error value.
 	///
@@ -83,25 +166,53 @@ impl Error {
 	/// always valid, and `StringDecode` should be impossible because all strings are taken as
 	/// string-slices (`&str`) which are always valid UTF-8.
 	pub fn from_isize(value: isize) -> Result<usize> {
+		if value < 0 {
+			Err(Self::from_negative(
+				i32::try_from(value).unwrap_or(Self::UNKNOWN_CODE),
+			))
+		} else {
+			// Cast from isize to usize is safe because the value is ≥ 0.
+			#[allow(clippy::cast_sign_loss)]
+			Ok(value as usize)
+		}
+	}
+
+	/// Checks a system call return value of type `i64` for an error value.
+	///
+	/// Returns a `Result` containing an `Error` if the value is negative, or the original value if
+	/// it was nonnegative.
+	///
+	/// This is equivalent to [`from_isize`](Error::from_isize), but is convenient when the return
+	/// value comes from somewhere other than a real system call, such as a test harness on a
+	/// 64-bit host.
+	///
+	/// # Errors
+	/// This function fails if the parameter is negative, decoding the represented error code.
+	///
+	/// # Panics
+	/// This function panics if the syscall error code is `MemoryFault` or `StringDecode`, for the
+	/// same reasons as [`from_isize`](Error::from_isize).
+	pub fn from_i64(value: i64) -> Result<u64> {
+		if value < 0 {
+			Err(Self::from_negative(
+				i32::try_from(value).unwrap_or(Self::UNKNOWN_CODE),
+			))
+		} else {
+			// Cast from i64 to u64 is safe because the value is ≥ 0.
+			#[allow(clippy::cast_sign_loss)]
+			Ok(value as u64)
+		}
+	}
+
+	/// Decodes a negative system call return value into an error.
+	///
+	/// # Panics
+	/// This function panics if the syscall error code is `MemoryFault` or `StringDecode`.
+	fn from_negative(value: i32) -> Self {
 		match value {
 			-1 => panic_or_trap!("Memory fault"), // Impossible due to memory safety
-			-2 => Err(Self::CborDecode),
 			-3 => panic_or_trap!("String decode error"), // Impossible due to type safety of &str
-			-4 => Err(Self::BufferTooShort),
-			-5 => Err(Self::NoSuchComponent),
-			-6 => Err(Self::NoSuchMethod),
-			-7 => Err(Self::BadParameters),
-			-8 => Err(Self::QueueFull),
-			-9 => Err(Self::QueueEmpty),
-			-10 => Err(Self::BadDescriptor),
-			-11 => Err(Self::TooManyDescriptors),
-			-12 => Err(Self::Other),
-			x if x < 0 => Err(Self::Unknown),
-			_ => {
-				// Cast from isize to usize is safe because the match arm verifies that x ≥ 0.
-				#[allow(clippy::cast_sign_loss)]
-				Ok(value as usize)
-			}
+			_ => Self::from_code(value),
 		}
 	}
 
@@ -128,7 +239,72 @@ impl Error {
 	}
 }
 
-#[cfg(feature = "std")]
+#[cfg(all(feature = "std", not(feature = "core-error")))]
 impl std::error::Error for Error {}
 
+#[cfg(feature = "core-error")]
+impl core::error::Error for Error {}
+
 pub type Result<T> = core::result::Result<T, Error>;
+
+/// Extension methods for turning expected errors into `None`.
+pub trait ResultExt<T> {
+	/// Converts “not found” errors into `Ok(None)`.
+	///
+	/// [`NoSuchComponent`](Error::NoSuchComponent) and [`NoSuchMethod`](Error::NoSuchMethod) become
+	/// `Ok(None)`, success becomes `Ok(Some(…))`, and all other errors are passed through.
+	///
+	/// # Errors
+	/// Any error other than `NoSuchComponent` or `NoSuchMethod` is returned unchanged.
+	fn optional_not_found(self) -> Result<Option<T>>;
+
+	/// Converts [`QueueEmpty`](Error::QueueEmpty) into `Ok(None)`.
+	///
+	/// Success becomes `Ok(Some(…))`, and all other errors are passed through.
+	///
+	/// # Errors
+	/// Any error other than `QueueEmpty` is returned unchanged.
+	fn optional_empty(self) -> Result<Option<T>>;
+}
+
+impl<T> ResultExt<T> for Result<T> {
+	fn optional_not_found(self) -> Result<Option<T>> {
+		match self {
+			Ok(value) => Ok(Some(value)),
+			Err(Error::NoSuchComponent | Error::NoSuchMethod) => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+
+	fn optional_empty(self) -> Result<Option<T>> {
+		match self {
+			Ok(value) => Ok(Some(value)),
+			Err(Error::QueueEmpty) => Ok(None),
+			Err(e) => Err(e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn from_code_round_trips_syscall_errors() {
+		for code in -12..=-2 {
+			let error = Error::from_code(code);
+			if error != Error::Unknown {
+				assert_eq!(error.code(), code);
+			}
+		}
+	}
+
+	#[test]
+	fn from_code_does_not_produce_duplicate_descriptor() {
+		assert_eq!(
+			Error::from_code(Error::DUPLICATE_DESCRIPTOR_CODE),
+			Error::Unknown
+		);
+		assert_eq!(Error::from_code(Error::UNKNOWN_CODE), Error::Unknown);
+	}
+}
@@ -1,7 +1,12 @@
 //! Functions for loading and executing a new WASM binary.
 
+use super::computer::installed_ram_usize;
 use super::error::{Error, Result};
-use oc_wasm_sys::execute as sys;
+use super::sys::execute as sys;
+use core::fmt::{Debug, Display, Formatter};
+
+/// The expected Wasm module header: the magic number `\0asm` followed by version 1.
+const WASM_HEADER: [u8; 8] = *b"\0asm\x01\0\0\0";
 
 /// Clears the execution buffer.
 ///
@@ -28,8 +33,289 @@ pub fn add(data: &[u8]) -> Result<()> {
 	Ok(())
 }
 
+/// Checks that the first chunk of a binary starts with a Wasm module header.
+///
+/// The `first_chunk` parameter is the first portion of the Wasm binary, before it is passed to
+/// [`add`](add). This allows a wrong file to be detected before any of it is loaded, rather than
+/// by a trap on [`execute`](execute). The chunk must contain at least the whole eight-byte
+/// header; a caller that receives the binary in smaller pieces should use
+/// [`Validator`](Validator) or [`Loader`](Loader) instead.
+///
+/// Neither [`add`](add) nor [`execute`](execute) checks the header or keeps track of the total
+/// size loaded; use [`Validator`](Validator) or [`Loader`](Loader) for that.
+///
+/// # Errors
+/// * [`BadParameters`](Error::BadParameters) is returned if `first_chunk` is shorter than the
+///   header or does not start with the magic number `\0asm` followed by version 1.
+pub fn validate_magic(first_chunk: &[u8]) -> Result<()> {
+	if first_chunk.starts_with(&WASM_HEADER) {
+		Ok(())
+	} else {
+		Err(Error::BadParameters)
+	}
+}
+
 /// Executes the Wasm binary contained in the execution buffer.
 pub fn execute() -> ! {
 	// SAFETY: execute is unconditionally safe.
 	unsafe { sys::execute() }
 }
+
+/// A wrapper around the execution buffer that checks the Wasm binary header as it is loaded.
+///
+/// The execution buffer cannot be read back once data has been written to it. A value of this
+/// type forwards data to the execution buffer while keeping a copy of the first eight bytes, which
+/// form the Wasm module header. Before calling [`execute`](execute), the caller can use
+/// [`header_ok`](Validator::header_ok) to detect a corrupted or non-Wasm binary.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Validator {
+	/// The first bytes of the binary.
+	header: [u8; 8],
+
+	/// The number of bytes of `header` that have been filled.
+	header_len: usize,
+}
+
+impl Validator {
+	/// Creates a validator for a binary that has not yet been loaded.
+	///
+	/// The execution buffer must be empty, either because nothing has been loaded yet in this run
+	/// or because [`clear`](Validator::clear) has been called.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn new() -> Self {
+		Self {
+			header: [0; 8],
+			header_len: 0,
+		}
+	}
+
+	/// Clears the execution buffer and forgets any header bytes seen so far.
+	pub fn clear(&mut self) {
+		clear();
+		*self = Self::new();
+	}
+
+	/// Writes data to the execution buffer.
+	///
+	/// The `data` parameter is the portion of the Wasm binary to write into the buffer.
+	///
+	/// # Errors
+	/// * [`Other`](Error::Other) is returned if this call would make the contents of the buffer
+	///   larger than the computer’s installed RAM.
+	///
+	/// On error, nothing is written and no header bytes are recorded.
+	pub fn add(&mut self, data: &[u8]) -> Result<()> {
+		add(data)?;
+		let header_remaining = &mut self.header[self.header_len..];
+		let n = header_remaining.len().min(data.len());
+		header_remaining[..n].copy_from_slice(&data[..n]);
+		self.header_len += n;
+		Ok(())
+	}
+
+	/// Returns whether the data written so far starts with a valid Wasm version 1 module header.
+	///
+	/// If fewer than eight bytes have been written, `false` is returned.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn header_ok(&self) -> bool {
+		self.header_len == self.header.len() && self.header == WASM_HEADER
+	}
+}
+
+/// A wrapper around the execution buffer that keeps track of how much more data it can hold.
+///
+/// The execution buffer can hold at most as many bytes as the computer has installed RAM. A value
+/// of this type counts the bytes written, allowing the caller to find out how much room is left
+/// before starting a write (for example, to decide how large a chunk to download next).
+///
+/// If the `checksum` feature is enabled, a value of this type also computes a CRC-32 of the bytes
+/// written, which the caller can compare against a known-good value before calling
+/// [`execute`](execute).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Loader {
+	/// The number of bytes written to the execution buffer.
+	len: usize,
+
+	/// The CRC-32 of the bytes written to the execution buffer.
+	#[cfg(feature = "checksum")]
+	crc: u32,
+}
+
+impl Loader {
+	/// Creates a loader for a binary that has not yet been loaded.
+	///
+	/// The execution buffer must be empty, either because nothing has been loaded yet in this run
+	/// or because [`clear`](clear) has been called.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn new() -> Self {
+		Self {
+			len: 0,
+			#[cfg(feature = "checksum")]
+			crc: 0,
+		}
+	}
+
+	/// Returns the number of bytes written to the execution buffer so far.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns whether nothing has been written to the execution buffer yet.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the number of bytes that can still be written to the execution buffer.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn remaining(&self) -> usize {
+		installed_ram_usize().saturating_sub(self.len)
+	}
+
+	/// Returns the CRC-32 of the bytes written to the execution buffer so far.
+	///
+	/// The checksum is the common CRC-32 variant used by zlib, PNG, and Ethernet (reflected
+	/// polynomial `0xEDB88320`, initial value and final XOR of `0xFFFFFFFF`). If nothing has been
+	/// written, zero is returned.
+	#[cfg(feature = "checksum")]
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn checksum(&self) -> u32 {
+		self.crc
+	}
+
+	/// Clears the execution buffer and resets the byte count (and checksum, if enabled) to zero.
+	pub fn reset(&mut self) {
+		clear();
+		*self = Self::new();
+	}
+
+	/// Writes data to the execution buffer.
+	///
+	/// The `data` parameter is the portion of the Wasm binary to write into the buffer.
+	///
+	/// On success, the number of bytes that can still be written is returned.
+	///
+	/// # Errors
+	/// * [`Other`](Error::Other) is returned if this call would make the contents of the buffer
+	///   larger than the computer’s installed RAM.
+	///
+	/// On error, nothing is written.
+	pub fn add(&mut self, data: &[u8]) -> Result<usize> {
+		if data.len() > self.remaining() {
+			return Err(Error::Other);
+		}
+		add(data)?;
+		self.len += data.len();
+		#[cfg(feature = "checksum")]
+		{
+			self.crc = crc32_update(self.crc, data);
+		}
+		Ok(self.remaining())
+	}
+}
+
+/// Extends a CRC-32 over additional data.
+///
+/// The `crc` parameter is the CRC-32 of the data seen so far (zero if none), and the return value
+/// is the CRC-32 of that data followed by `data`. A bitwise implementation is used rather than a
+/// lookup table to keep code size down; loading a binary is not performance-critical.
+#[cfg(feature = "checksum")]
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+	let mut crc = !crc;
+	for &byte in data {
+		crc ^= u32::from(byte);
+		for _ in 0..8 {
+			let mask = (crc & 1).wrapping_neg();
+			crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+		}
+	}
+	!crc
+}
+
+/// The errors that can occur while loading a binary with [`run_from`](run_from).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ExecuteError<E> {
+	/// Writing a chunk to the execution buffer failed.
+	Load(Error),
+
+	/// The source of the chunks reported an error.
+	Source(E),
+}
+
+impl<E: Display> Display for ExecuteError<E> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Load(e) => Display::fmt(e, f),
+			Self::Source(e) => Display::fmt(e, f),
+		}
+	}
+}
+
+#[cfg(all(feature = "std", not(feature = "core-error")))]
+impl<E: Debug + Display> std::error::Error for ExecuteError<E> {}
+
+#[cfg(feature = "core-error")]
+impl<E: Debug + Display> core::error::Error for ExecuteError<E> {}
+
+/// Loads a Wasm binary from a sequence of chunks and executes it.
+///
+/// The `chunks` parameter yields the binary in pieces, each of which is either a portion of the
+/// binary or an error. The execution buffer is cleared, each chunk is written to it in turn, and
+/// then the binary is executed.
+///
+/// This function only returns if an error occurs, in which case the execution buffer is cleared
+/// before returning.
+///
+/// # Errors
+/// * [`Load`](ExecuteError::Load) containing [`Other`](Error::Other) is returned if the binary is
+///   larger than the computer’s installed RAM.
+/// * [`Source`](ExecuteError::Source) is returned if `chunks` yields an error.
+pub fn run_from<'a, I, E>(chunks: I) -> ExecuteError<E>
+where
+	I: IntoIterator<Item = core::result::Result<&'a [u8], E>>,
+{
+	let mut loader = Loader::new();
+	loader.reset();
+	for chunk in chunks {
+		let result = match chunk {
+			Ok(chunk) => loader.add(chunk).map_err(ExecuteError::Load),
+			Err(e) => Err(ExecuteError::Source(e)),
+		};
+		if let Err(e) = result {
+			loader.reset();
+			return e;
+		}
+	}
+	execute()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn validate_magic_accepts_header() {
+		assert_eq!(validate_magic(&WASM_HEADER), Ok(()));
+		assert_eq!(validate_magic(b"\0asm\x01\0\0\0\x01\x04"), Ok(()));
+	}
+
+	#[test]
+	fn validate_magic_rejects_short() {
+		assert_eq!(validate_magic(b""), Err(Error::BadParameters));
+		assert_eq!(validate_magic(b"\0asm"), Err(Error::BadParameters));
+		assert_eq!(validate_magic(b"\0asm\x01\0\0"), Err(Error::BadParameters));
+	}
+
+	#[test]
+	fn validate_magic_rejects_wrong_magic() {
+		assert_eq!(
+			validate_magic(b"\x7FELF\x01\0\0\0"),
+			Err(Error::BadParameters)
+		);
+		assert_eq!(
+			validate_magic(b"\0asm\x02\0\0\0"),
+			Err(Error::BadParameters)
+		);
+	}
+}
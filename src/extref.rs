@@ -8,11 +8,20 @@
 //! memory location, eliminating the need to allocate enough memory and copy the data into the CBOR
 //! output.
 
+use core::fmt::{Debug, Formatter};
 use minicbor::data::Tag;
 use minicbor::encode::{Encode, Encoder, Write};
 
 /// The External Reference CBOR tag number.
-const EXTERNAL_REFERENCE: Tag = Tag::new(32769);
+pub(crate) const EXTERNAL_REFERENCE: Tag = Tag::new(32769);
+
+/// The maximum length, in bytes, of the CBOR encoding of a [`Bytes`](Bytes) or
+/// [`String`](String) reference.
+///
+/// A reference is encoded as a three-byte External Reference tag, a one-byte array header, a
+/// one-byte major type, and two unsigned integers of up to five bytes each; references with small
+/// pointers or lengths take fewer.
+pub const MAX_CBOR_LEN: usize = 15;
 
 /// A reference to a byte array.
 pub struct Bytes<'a>(&'a [u8]);
@@ -30,6 +39,30 @@ impl<'a> Bytes<'a> {
 	pub const unsafe fn new(data: &'a [u8]) -> Self {
 		Self(data)
 	}
+
+	/// Returns the referenced byte array.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn as_slice(&self) -> &'a [u8] {
+		self.0
+	}
+
+	/// Returns the length of the referenced byte array, in bytes.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Returns whether the referenced byte array is empty.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl Debug for Bytes<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("Bytes").field("len", &self.len()).finish()
+	}
 }
 
 impl<'a, C> Encode<C> for Bytes<'a> {
@@ -50,6 +83,36 @@ impl<'a, C> Encode<C> for Bytes<'a> {
 	}
 }
 
+/// Encodes a byte array either inline or as an external reference, depending on its length.
+///
+/// The `e` parameter is the encoder to write to. The `data` parameter is the byte array to encode.
+/// The `threshold` parameter is the largest length that is encoded inline; any longer byte array
+/// is encoded as an external reference, as by [`Bytes`](Bytes).
+///
+/// On success, `true` is returned if an external reference was written, or `false` if `data` was
+/// copied inline.
+///
+/// # Errors
+/// Any error returned by `e` is returned by this function.
+///
+/// # Safety
+/// If this function returns `true`, the caller must ensure that `data` remains in existence and
+/// unmodified until the CBOR data has been submitted as part of a method call, for the same reason
+/// as in [`Bytes::new`](Bytes::new).
+pub unsafe fn encode_bytes_auto<W: Write>(
+	e: &mut Encoder<W>,
+	data: &[u8],
+	threshold: usize,
+) -> Result<bool, minicbor::encode::Error<W::Error>> {
+	if data.len() <= threshold {
+		e.bytes(data)?;
+		Ok(false)
+	} else {
+		e.encode(Bytes::new(data))?;
+		Ok(true)
+	}
+}
+
 /// A reference to a text string.
 pub struct String<'a>(&'a str);
 
@@ -66,6 +129,33 @@ impl<'a> String<'a> {
 	pub const unsafe fn new(data: &'a str) -> Self {
 		Self(data)
 	}
+
+	/// Returns the referenced string.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn as_str(&self) -> &'a str {
+		self.0
+	}
+
+	/// Returns the length of the referenced string, in bytes.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Returns whether the referenced string is empty.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl Debug for String<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("String")
+			.field("len", &self.len())
+			.field("text", &self.0)
+			.finish()
+	}
 }
 
 impl<'a, C> Encode<C> for String<'a> {
@@ -85,3 +175,159 @@ impl<'a, C> Encode<C> for String<'a> {
 		Ok(())
 	}
 }
+
+/// Encodes a string either inline or as an external reference, depending on its length.
+///
+/// The `e` parameter is the encoder to write to. The `data` parameter is the string to encode. The
+/// `threshold` parameter is the largest length, in bytes, that is encoded inline; any longer
+/// string is encoded as an external reference, as by [`String`](String).
+///
+/// On success, `true` is returned if an external reference was written, or `false` if `data` was
+/// copied inline.
+///
+/// # Errors
+/// Any error returned by `e` is returned by this function.
+///
+/// # Safety
+/// If this function returns `true`, the caller must ensure that `data` remains in existence and
+/// unmodified until the CBOR data has been submitted as part of a method call, for the same reason
+/// as in [`String::new`](String::new).
+pub unsafe fn encode_str_auto<W: Write>(
+	e: &mut Encoder<W>,
+	data: &str,
+	threshold: usize,
+) -> Result<bool, minicbor::encode::Error<W::Error>> {
+	if data.len() <= threshold {
+		e.str(data)?;
+		Ok(false)
+	} else {
+		e.encode(String::new(data))?;
+		Ok(true)
+	}
+}
+
+/// A CBOR encoder that automatically uses external references for large byte arrays and strings.
+///
+/// A value of this type wraps a `minicbor` [`Encoder`](Encoder) along with a size threshold. Byte
+/// arrays and strings encoded via [`encode_bytes`](AutoRefEncoder::encode_bytes) and
+/// [`encode_str`](AutoRefEncoder::encode_str) are copied inline if they are no longer than the
+/// threshold, and written as External Reference objects otherwise. Other data items can be
+/// written via [`encoder`](AutoRefEncoder::encoder).
+///
+/// The caller still owns the referents of any external references that are written and must keep
+/// them alive and unmodified until the CBOR data has been submitted as part of a method call.
+#[derive(Debug)]
+pub struct AutoRefEncoder<W> {
+	/// The underlying encoder.
+	encoder: Encoder<W>,
+
+	/// The largest length, in bytes, that is encoded inline.
+	threshold: usize,
+}
+
+impl<W: Write> AutoRefEncoder<W> {
+	/// Creates an encoder writing to `writer`.
+	///
+	/// The `threshold` parameter is the largest length, in bytes, of a byte array or string that is
+	/// encoded inline rather than as an external reference.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn new(writer: W, threshold: usize) -> Self {
+		Self {
+			encoder: Encoder::new(writer),
+			threshold,
+		}
+	}
+
+	/// Returns the threshold.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn threshold(&self) -> usize {
+		self.threshold
+	}
+
+	/// Returns the underlying encoder, which can be used to write other data items.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn encoder(&mut self) -> &mut Encoder<W> {
+		&mut self.encoder
+	}
+
+	/// Destroys the `AutoRefEncoder` and returns the underlying writer.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn into_writer(self) -> W {
+		self.encoder.into_writer()
+	}
+
+	/// Encodes a byte array inline or as an external reference, depending on its length.
+	///
+	/// On success, `true` is returned if an external reference was written, or `false` if `data`
+	/// was copied inline.
+	///
+	/// # Errors
+	/// Any error returned by the underlying encoder is returned by this function.
+	///
+	/// # Safety
+	/// The same requirements as [`encode_bytes_auto`](encode_bytes_auto) apply.
+	pub unsafe fn encode_bytes(
+		&mut self,
+		data: &[u8],
+	) -> Result<bool, minicbor::encode::Error<W::Error>> {
+		encode_bytes_auto(&mut self.encoder, data, self.threshold)
+	}
+
+	/// Encodes a string inline or as an external reference, depending on its length.
+	///
+	/// On success, `true` is returned if an external reference was written, or `false` if `data`
+	/// was copied inline.
+	///
+	/// # Errors
+	/// Any error returned by the underlying encoder is returned by this function.
+	///
+	/// # Safety
+	/// The same requirements as [`encode_str_auto`](encode_str_auto) apply.
+	pub unsafe fn encode_str(
+		&mut self,
+		data: &str,
+	) -> Result<bool, minicbor::encode::Error<W::Error>> {
+		encode_str_auto(&mut self.encoder, data, self.threshold)
+	}
+}
+
+/// An array of external references.
+///
+/// A value of this type wraps a slice of external references (such as [`Bytes`](Bytes) or
+/// [`String`](String)). When CBOR-encoded, it produces an array containing each element’s External
+/// Reference object, allowing several large byte arrays or strings to be passed in one parameter
+/// without copying them.
+///
+/// Every element carries the same requirement as the element type itself: the caller must ensure
+/// that every referent remains in existence until the CBOR data has been submitted as part of a
+/// method call.
+#[derive(Debug)]
+pub struct Array<'a, T>(&'a [T]);
+
+impl<'a, T> Array<'a, T> {
+	/// Wraps a slice of external references.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn new(elements: &'a [T]) -> Self {
+		Self(elements)
+	}
+
+	/// Returns the wrapped slice.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn as_slice(&self) -> &'a [T] {
+		self.0
+	}
+}
+
+impl<T: Encode<C>, C> Encode<C> for Array<'_, T> {
+	fn encode<W: Write>(
+		&self,
+		e: &mut Encoder<W>,
+		ctx: &mut C,
+	) -> Result<(), minicbor::encode::Error<W::Error>> {
+		e.array(self.0.len() as u64)?;
+		for element in self.0 {
+			element.encode(e, ctx)?;
+		}
+		Ok(())
+	}
+}
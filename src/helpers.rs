@@ -1,6 +1,27 @@
 use super::error::{Error, Result};
 use core::ptr;
 
+/// Checks that a system call did not report writing more bytes than fit in its buffer.
+///
+/// `bytes_written` is the length returned by the system call. `len` is the length of the buffer
+/// that was passed to it.
+///
+/// OC-Wasm promises never to do this, so the check is only performed in debug builds, where it
+/// turns a broken promise into a clear failure rather than an out-of-bounds slice.
+///
+/// # Panics
+/// In debug builds, this function panics if `bytes_written` is greater than `len`.
+#[inline]
+pub fn check_written(bytes_written: usize, len: usize) {
+	if cfg!(debug_assertions) && bytes_written > len {
+		panic_or_trap!(
+			"system call reported writing {} bytes into a {}-byte buffer",
+			bytes_written,
+			len
+		);
+	}
+}
+
 /// Calls a function and passes an optional string.
 ///
 /// `f` is the function. `s` is the string.
@@ -51,6 +72,7 @@ pub unsafe fn call_buffer(
 	let len = buf.len();
 	let ptr = buf.as_mut_ptr();
 	let bytes_written = Error::from_isize(f(ptr, len))?;
+	check_written(bytes_written, len);
 	Ok(buf.get_unchecked_mut(0..bytes_written))
 }
 
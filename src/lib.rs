@@ -7,8 +7,50 @@
 //! feature is disabled, a Wasm `unreachable` (trap) instruction is executed instead; this produces
 //! smaller code but less useful error messages.
 //!
+//! The `manual-descriptors` feature disables automatic closing of
+//! [`descriptor::Owned`](descriptor::Owned) values when they are dropped. When the feature is
+//! enabled, dropping an `Owned` leaks the descriptor; the application is responsible for calling
+//! [`descriptor::Owned::close`](descriptor::Owned::close) on every descriptor it obtains. Leaked
+//! descriptors are only released when the computer shuts down, and OC-Wasm limits the number of
+//! open descriptors, so this feature should only be used by applications that manage descriptor
+//! lifetimes very carefully.
+//!
+//! The `debug-descriptors` feature keeps a count of live [`descriptor::Owned`](descriptor::Owned)
+//! values, available from `descriptor::open_count`, to help track down descriptor leaks.
+//!
+//! The `debug-calls` feature makes each [`component::MethodCall`](component::MethodCall) record
+//! the component address or descriptor and the method name it was started with, and include them
+//! in its `Debug` output, so that a failed call can be identified during development.
+//!
+//! The `alloc` feature enables APIs that require dynamic memory allocation, such as those that
+//! return a `Vec`, and also enables `minicbor`’s own `alloc` feature so that owned types such as
+//! `String` can be decoded. It is enabled automatically by the `std` feature.
+//!
+//! The `checksum` feature makes [`execute::Loader`](execute::Loader) compute a CRC-32 of the
+//! binary as it is loaded, so that corruption can be detected before execution begins.
+//!
+//! The `mock` feature, which only has an effect on non-Wasm targets, replaces the OC-Wasm system
+//! calls with a simulated computer implemented in Rust, so that code using this crate can be unit
+//! tested on the host. See the `mock` module for details. It implies `std`.
+//!
+//! The `defmt` feature implements `defmt::Format` for [`Address`](Address),
+//! [`error::Error`](error::Error), [`component::InvokeResult`](component::InvokeResult), and
+//! [`component::MethodAttributes`](component::MethodAttributes), for logging over a `defmt`
+//! transport.
+//!
+//! The `serde` feature implements `serde::Serialize` and `serde::Deserialize` for
+//! [`component::MethodAttributes`](component::MethodAttributes) (as a struct of three booleans)
+//! and [`component::InvokeResult`](component::InvokeResult) (as an externally tagged enum), so
+//! that method metadata can be exchanged with other tools.
+//!
 //! The `std` feature controls whether [`error::Error`](error::Error) implements
 //! `std::error::Error`, which it cannot do in a `no_std` environment.
+//!
+//! The `core-error` feature makes [`error::Error`](error::Error) and
+//! [`component::MethodCallError`](component::MethodCallError) implement `core::error::Error`,
+//! which is available even in a `no_std` environment. It requires Rust 1.81 or newer. Because
+//! `std::error::Error` is a re-export of `core::error::Error`, the implementation is also usable
+//! as `std::error::Error` when the standard library is available.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![warn(
@@ -35,12 +77,76 @@
 	clippy::pedantic,
 )]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+/// Declares the storage for a type of which only one value may ever be taken.
+///
+/// The storage starts out holding `value`, which can be removed with
+/// `take_singleton` and put back with `restore_singleton`.
+/// When the mock backend is in use, host tests may run on several threads, each with its own mock
+/// registry, so each thread gets its own instance.
+macro_rules! singleton {
+	($slot: ident: $t: ty = $value: expr) => {
+		#[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
+		static mut $slot: Option<$t> = Some($value);
+
+		#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+		std::thread_local! {
+			static $slot: core::cell::Cell<Option<$t>> = core::cell::Cell::new(Some($value));
+		}
+	};
+}
+
+/// Returns the single instance of a type of which only one value may ever be taken.
+///
+/// The `slot` parameter is storage declared with `singleton`. The first evaluation
+/// returns `Some(value)`; subsequent evaluations return `None`, unless the value is restored.
+macro_rules! take_singleton {
+	($slot: ident) => {{
+		#[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
+		{
+			// SAFETY: Wasm doesn’t have threads, so only one caller can get here at a time, and
+			// the Option will be empty for all but the first caller.
+			unsafe { $slot.take() }
+		}
+		#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+		{
+			$slot.with(core::cell::Cell::take)
+		}
+	}};
+}
+
+/// Puts the single instance of a type, previously taken with `take_singleton`,
+/// back into its storage, so that the next evaluation of `take_singleton` returns it again.
+macro_rules! restore_singleton {
+	($slot: ident, $value: expr) => {{
+		let value = $value;
+		#[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
+		{
+			// SAFETY: Wasm doesn’t have threads, so only one caller can get here at a time. The
+			// value was taken, so only its holder, which is giving it up, can call this.
+			unsafe { $slot = Some(value) };
+		}
+		#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+		{
+			$slot.with(|slot| slot.set(Some(value)));
+		}
+	}};
+}
+
 pub mod component;
 pub mod computer;
 pub mod descriptor;
 pub mod error;
 pub mod execute;
 pub mod extref;
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+pub mod mock;
+pub mod prelude;
+pub mod scratch;
+#[cfg(feature = "alloc")]
+pub mod value;
 
 use core::fmt::{Display, Formatter};
 use core::str::FromStr;
@@ -50,12 +156,24 @@ use minicbor::{
 };
 use uuid::Uuid;
 
+#[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
+use oc_wasm_sys as sys;
+
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+use mock::sys;
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+use oc_wasm_sys as _;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 /// A component address.
 ///
 /// This is just a UUID. It supports `minicbor`. When encoding, it encodes as a byte string tagged
 /// with the Binary UUID tag. When decoding, it decodes from an optional Binary UUID (or
-/// Identifier, for backwards compatibility) tag followed by either a byte string or a UTF-8
-/// string.
+/// Identifier, for backwards compatibility) tag followed by either a 16-byte byte string or a
+/// UTF-8 string holding the UUID in hyphenated (36-character) or simple (32-character) form.
+/// Anything else is rejected.
 #[derive(Clone, Copy, Debug, Default, Hash, Eq, Ord, PartialEq, PartialOrd)]
 #[repr(transparent)]
 pub struct Address(Uuid);
@@ -63,6 +181,12 @@ pub struct Address(Uuid);
 impl Address {
 	const TAG: Tag = Tag::new(37);
 
+	/// The length, in bytes, of the CBOR encoding of an address.
+	///
+	/// The encoding is always a two-byte Binary UUID tag followed by a one-byte byte string header
+	/// and the 16 bytes of the UUID.
+	pub const CBOR_LEN: usize = 19;
+
 	#[must_use = "This function is only useful for its return value"]
 	pub const fn as_bytes(&self) -> &[u8; 16] {
 		self.0.as_bytes()
@@ -72,6 +196,24 @@ impl Address {
 	pub const fn from_bytes(b: [u8; 16]) -> Self {
 		Self(Uuid::from_bytes(b))
 	}
+
+	/// Returns an abbreviated form of the address for compact display.
+	///
+	/// The `buffer` parameter identifies where to store the abbreviation.
+	///
+	/// The abbreviation is the first eight hexadecimal digits of the address, in lowercase, as
+	/// commonly shown in the in-game console. It is written into `buffer` and a string slice
+	/// referring to it is returned. The full address can still be displayed using the `Display`
+	/// impl.
+	pub fn short<'buf>(&self, buffer: &'buf mut [u8; 8]) -> &'buf str {
+		const DIGITS: &[u8; 16] = b"0123456789abcdef";
+		for (i, byte) in self.as_bytes()[..4].iter().enumerate() {
+			buffer[i * 2] = DIGITS[usize::from(byte >> 4)];
+			buffer[i * 2 + 1] = DIGITS[usize::from(byte & 0x0F)];
+		}
+		// SAFETY: Every byte written is an ASCII hexadecimal digit.
+		unsafe { core::str::from_utf8_unchecked(buffer) }
+	}
 }
 
 impl Display for Address {
@@ -80,6 +222,13 @@ impl Display for Address {
 	}
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Address {
+	fn format(&self, f: defmt::Formatter<'_>) {
+		defmt::write!(f, "{}", defmt::Display2Format(self));
+	}
+}
+
 impl FromStr for Address {
 	type Err = <Uuid as FromStr>::Err;
 
@@ -115,6 +264,11 @@ impl<Context> decode::Decode<'_, Context> for Address {
 			}
 			Type::String => {
 				let s = d.str()?;
+				if !matches!(s.len(), 32 | 36) {
+					return Err(decode::Error::message(
+						"expected 32- or 36-character UUID string",
+					));
+				}
 				Ok(
 					Self::from_str(s)
 						.map_err(|_| decode::Error::message("expected UUID string"))?,
@@ -136,30 +290,138 @@ impl<Context> encode::Encode<Context> for Address {
 	}
 }
 
+/// Returns the addresses that are present in `a` but not in `b`.
+///
+/// The returned addresses are in the same order as they appear in `a`. Duplicates in `a` are
+/// preserved. Neither slice needs to be sorted.
+#[cfg(feature = "alloc")]
+#[must_use = "This function is only useful for its return value"]
+pub fn address_set_diff(a: &[Address], b: &[Address]) -> Vec<Address> {
+	let b = sorted_addresses(b);
+	a.iter()
+		.filter(|i| b.binary_search(i).is_err())
+		.copied()
+		.collect()
+}
+
+/// Returns the addresses that are present in both `a` and `b`.
+///
+/// The returned addresses are in the same order as they appear in `a`. Duplicates in `a` are
+/// preserved. Neither slice needs to be sorted.
+#[cfg(feature = "alloc")]
+#[must_use = "This function is only useful for its return value"]
+pub fn address_set_intersect(a: &[Address], b: &[Address]) -> Vec<Address> {
+	let b = sorted_addresses(b);
+	a.iter()
+		.filter(|i| b.binary_search(i).is_ok())
+		.copied()
+		.collect()
+}
+
+/// Returns a sorted copy of a slice of addresses, suitable for binary searching.
+#[cfg(feature = "alloc")]
+fn sorted_addresses(addresses: &[Address]) -> Vec<Address> {
+	let mut ret = addresses.to_vec();
+	ret.sort_unstable();
+	ret
+}
+
 /// Panics or traps depending on the state of the `panic` feature.
 ///
-/// If the `panic` feature is enabled, this macro panics with the given message. If it is disabled,
-/// this macro invokes Wasm `UNREACHABLE` (trap) instruction, instantly terminating execution; the
-/// message is ignored.
+/// If the `panic` feature is enabled, this macro panics with the given message, which may be a
+/// format string followed by arguments as for `core::panic!`. If it is disabled, this macro
+/// invokes Wasm `UNREACHABLE` (trap) instruction, instantly terminating execution; the message is
+/// ignored.
 #[cfg(feature = "panic")]
 #[macro_export]
 macro_rules! panic_or_trap {
-	($message: literal) => {
-		core::panic!($message)
+	($message: literal $(, $arg: expr)* $(,)?) => {
+		core::panic!($message $(, $arg)*)
 	};
 }
 
 /// Panics or traps depending on the state of the `panic` feature.
 ///
-/// If the `panic` feature is enabled, this macro panics with the given message. If it is disabled,
-/// this macro invokes Wasm `UNREACHABLE` (trap) instruction, instantly terminating execution; the
-/// message is ignored.
+/// If the `panic` feature is enabled, this macro panics with the given message, which may be a
+/// format string followed by arguments as for `core::panic!`. If it is disabled, this macro
+/// invokes Wasm `UNREACHABLE` (trap) instruction, instantly terminating execution; the message is
+/// ignored, and the arguments are evaluated but not formatted.
 #[cfg(not(feature = "panic"))]
 #[macro_export]
 macro_rules! panic_or_trap {
-	($message: literal) => {
+	($message: literal $(, $arg: expr)* $(,)?) => {{
+		$(let _ = &$arg;)*
 		core::arch::wasm32::unreachable()
-	};
+	}};
 }
 
 mod helpers;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const HYPHENATED: &str = "01234567-89ab-cdef-0123-456789abcdef";
+	const SIMPLE: &str = "0123456789abcdef0123456789abcdef";
+	const BYTES: [u8; 16] = [
+		0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD,
+		0xEF,
+	];
+
+	/// Encodes an optional tag followed by a byte string or UTF-8 string, then decodes the result
+	/// as an address.
+	fn decode_address(
+		tag: Option<u64>,
+		bytes: Option<&[u8]>,
+		text: Option<&str>,
+	) -> Option<Address> {
+		let mut buffer = [0_u8; 64];
+		let mut encoder = minicbor::Encoder::new(&mut buffer[..]);
+		if let Some(tag) = tag {
+			encoder.tag(Tag::new(tag)).unwrap();
+		}
+		if let Some(bytes) = bytes {
+			encoder.bytes(bytes).unwrap();
+		}
+		if let Some(text) = text {
+			encoder.str(text).unwrap();
+		}
+		decode::Decoder::new(&buffer).decode().ok()
+	}
+
+	#[test]
+	fn address_decode_accepts() {
+		let expected = Some(Address::from_bytes(BYTES));
+		for tag in [None, Some(37), Some(39)] {
+			assert_eq!(decode_address(tag, Some(&BYTES), None), expected);
+			assert_eq!(decode_address(tag, None, Some(HYPHENATED)), expected);
+			assert_eq!(decode_address(tag, None, Some(SIMPLE)), expected);
+		}
+	}
+
+	#[test]
+	fn address_decode_rejects() {
+		assert_eq!(decode_address(None, Some(&BYTES[..15]), None), None);
+		assert_eq!(decode_address(Some(40), Some(&BYTES), None), None);
+		assert_eq!(
+			decode_address(None, None, Some("0123456789abcdef0123456789abcdeg")),
+			None
+		);
+		assert_eq!(
+			decode_address(None, None, Some("not a uuid, but 36 characters long!!")),
+			None
+		);
+		assert_eq!(
+			decode_address(None, None, Some("{01234567-89ab-cdef-0123-456789abcdef}")),
+			None
+		);
+		assert_eq!(
+			decode_address(
+				None,
+				None,
+				Some("urn:uuid:01234567-89ab-cdef-0123-456789abcdef")
+			),
+			None
+		);
+	}
+}
@@ -0,0 +1,1258 @@
+//! A host-side stand-in for the OC-Wasm system calls, for use in unit tests.
+//!
+//! When the `mock` feature is enabled and the target is not Wasm, the wrappers in this crate call
+//! the functions in this module rather than importing system calls from OC-Wasm. Those functions
+//! operate on an in-process registry of components, methods, descriptors, and signals, which test
+//! code populates to build deterministic fixtures. For example,
+//! [`register_component`](register_component) makes a component visible to
+//! [`Lister`](crate::component::Lister) and callable through
+//! [`Invoker`](crate::component::Invoker), and signals pushed with
+//! [`push_signal`](crate::computer::push_signal) can be popped again with
+//! [`pull_signal`](crate::computer::pull_signal). System calls can also be counted with
+//! [`syscall_count`](syscall_count), or made to fail with [`fail_next`](fail_next).
+//!
+//! The registry is per-thread, so tests running in parallel do not interfere with one another.
+//! For the same reason, [`Lister::take`](crate::component::Lister::take),
+//! [`MethodLister::take`](crate::component::MethodLister::take), and
+//! [`Invoker::take`](crate::component::Invoker::take) each return a value once per thread rather
+//! than once per process.
+//!
+//! # Example
+//! ```
+//! use oc_wasm_safe::component::{Invoker, Lister, MethodAttributes};
+//! use oc_wasm_safe::{mock, Address};
+//!
+//! let address = Address::from_bytes([1; 16]);
+//! let attributes = MethodAttributes {
+//!     direct: true,
+//!     getter: false,
+//!     setter: false,
+//! };
+//! // The result is a CBOR array containing the single integer 42.
+//! mock::register_component(address, "example", &[("answer", attributes, &[0x81, 0x18, 42])]);
+//!
+//! let mut lister = Lister::take().unwrap();
+//! let mut listing = lister.start(Some("example"));
+//! let entry = listing.next().unwrap();
+//! assert_eq!(*entry.address(), address);
+//! let mut buffer = [0_u8; 16];
+//! assert_eq!(entry.type_name(&mut buffer).unwrap(), "example");
+//! assert!(listing.next().is_none());
+//!
+//! let mut invoker = Invoker::take().unwrap();
+//! let (_, call) = invoker.component_method(&address, "answer", None).unwrap();
+//! let len = call.end(&mut buffer).expect_done().unwrap();
+//! assert_eq!(&buffer[..len], &[0x81, 0x18, 42]);
+//! ```
+
+use super::component::MethodAttributes;
+use super::error::Error;
+use super::Address;
+use minicbor::decode::Decoder;
+use ordered_float::NotNan;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::rc::Rc;
+
+/// The error code for a string that is not valid UTF-8, which [`Error`](Error) cannot represent.
+const STRING_DECODE: i32 = -3;
+
+/// The system calls that can be counted with [`syscall_count`](syscall_count) and made to fail
+/// with [`fail_next`](fail_next): those that return an integer.
+const INTERCEPTED: &[&str] = &[
+	"component::list_start",
+	"component::list_next",
+	"component::list_type",
+	"component::component_type",
+	"component::slot",
+	"component::methods_start_component",
+	"component::methods_start_value",
+	"component::methods_next",
+	"component::documentation_component",
+	"component::documentation_value",
+	"component::invoke_component_method",
+	"component::invoke_value",
+	"component::invoke_value_indexed_read",
+	"component::invoke_value_indexed_write",
+	"component::invoke_value_method",
+	"component::invoke_end",
+	"component::last_exception_message",
+	"component::last_exception_is_type",
+	"computer::address",
+	"computer::tmpfs_address",
+	"computer::push_signal",
+	"computer::pull_signal",
+	"computer::acl_next",
+	"computer::add_user",
+	"computer::remove_user",
+	"computer::beep_pattern",
+	"computer::debug",
+	"descriptor::close",
+	"descriptor::dup",
+	"execute::add",
+];
+
+/// The properties of the simulated computer itself.
+///
+/// These values are returned by the corresponding functions in [`computer`](crate::computer).
+/// They can be changed with [`computer`](computer).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Computer {
+	/// The computer’s own address.
+	pub address: Address,
+
+	/// The address of the computer’s temporary filesystem.
+	pub tmpfs_address: Address,
+
+	/// The world time, in seconds, that the computer has been running.
+	pub uptime: NotNan<f64>,
+
+	/// The CPU time, in seconds, that the computer has consumed.
+	pub cpu_time: NotNan<f64>,
+
+	/// The in-game time, in ticks.
+	pub world_time: u64,
+
+	/// The amount of installed RAM, in bytes.
+	pub installed_ram: u32,
+
+	/// The amount of free RAM, in bytes.
+	pub free_ram: u32,
+
+	/// The amount of energy stored.
+	pub energy: NotNan<f64>,
+
+	/// The maximum amount of energy that can be stored.
+	pub max_energy: NotNan<f64>,
+
+	/// The maximum number of signals the signal queue can hold.
+	///
+	/// Once this many signals are queued, pushing another fails with
+	/// [`QueueFull`](Error::QueueFull). The default matches a real computer.
+	pub signal_queue_capacity: usize,
+
+	/// The maximum number of descriptors that can be open at once.
+	///
+	/// Once this many descriptors are open, duplicating another fails with
+	/// [`TooManyDescriptors`](Error::TooManyDescriptors). Descriptors opened by
+	/// [`new_descriptor`](new_descriptor) are not limited. The default is unlimited.
+	pub descriptor_limit: usize,
+}
+
+impl Default for Computer {
+	fn default() -> Self {
+		Self {
+			address: Address::default(),
+			tmpfs_address: Address::default(),
+			uptime: NotNan::default(),
+			cpu_time: NotNan::default(),
+			world_time: 0,
+			installed_ram: 4 * 1024 * 1024,
+			free_ram: 4 * 1024 * 1024,
+			energy: NotNan::default(),
+			max_energy: NotNan::default(),
+			signal_queue_capacity: 256,
+			descriptor_limit: usize::MAX,
+		}
+	}
+}
+
+/// A function that implements a simulated method.
+///
+/// The function receives the CBOR-encoded parameters and returns the CBOR-encoded result.
+type Handler = Rc<dyn Fn(&[u8]) -> Result<Vec<u8>, Error>>;
+
+/// A method registered on a component.
+struct Method {
+	/// The method name.
+	name: String,
+
+	/// The method attributes.
+	attributes: MethodAttributes,
+
+	/// The function that implements the method.
+	handler: Handler,
+}
+
+/// The state of a method call.
+enum Call {
+	/// The method is indirect and has not run yet; it will run on the next timeslice.
+	Pending(Handler, Vec<u8>),
+
+	/// The method has run and produced a result.
+	Done(Result<Vec<u8>, Error>),
+}
+
+/// A registered component.
+struct Component {
+	/// The component address.
+	address: Address,
+
+	/// The component type.
+	type_name: String,
+
+	/// The methods available on the component.
+	methods: Vec<Method>,
+}
+
+/// The complete state of the simulated computer.
+#[derive(Default)]
+struct State {
+	/// The registered components.
+	components: Vec<Component>,
+
+	/// The components remaining in the current component listing, with their types.
+	listing: VecDeque<(Address, String)>,
+
+	/// The type of the most recently listed component.
+	listed_type: Option<String>,
+
+	/// The methods remaining in the current method listing, with their attributes.
+	method_listing: VecDeque<(String, u32)>,
+
+	/// The method call in progress, if any.
+	call: Option<Call>,
+
+	/// The message of the most recent method call exception.
+	exception: String,
+
+	/// The open descriptors.
+	descriptors: BTreeSet<u32>,
+
+	/// The signal queue, with each signal in the form returned by `pull_signal`.
+	signals: VecDeque<Vec<u8>>,
+
+	/// The users in the access control list.
+	users: Vec<String>,
+
+	/// The position of the access control list iteration.
+	users_position: usize,
+
+	/// The number of bytes in the execution buffer.
+	execute_len: usize,
+
+	/// The properties of the computer.
+	computer: Computer,
+
+	/// The number of times each system call has been made.
+	syscall_counts: BTreeMap<&'static str, usize>,
+
+	/// The error codes to be returned by the next call to each system call.
+	failures: BTreeMap<&'static str, i32>,
+}
+
+std::thread_local! {
+	/// The state of the simulated computer for this thread.
+	static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// Runs a function with access to the state of the simulated computer.
+fn with_state<R>(f: impl FnOnce(&mut State) -> R) -> R {
+	STATE.with(|state| f(&mut state.borrow_mut()))
+}
+
+/// Discards all registered components, descriptors, signals, and other state, returning the
+/// simulated computer to the state it was in when the thread started.
+pub fn reset() {
+	with_state(|state| *state = State::default());
+}
+
+/// Registers a component.
+///
+/// The `address` parameter is the address of the component, and `component_type` is its type. The
+/// `methods` parameter lists the methods available on the component; each entry contains the
+/// method name, its attributes, and the CBOR-encoded result (normally an array) returned by every
+/// call to the method.
+///
+/// If a component with the same address is already registered, it is replaced.
+///
+/// # Panics
+/// This function panics if `component_type` is empty, as real components always have a type.
+pub fn register_component(
+	address: Address,
+	component_type: &str,
+	methods: &[(&str, MethodAttributes, &[u8])],
+) {
+	assert!(
+		!component_type.is_empty(),
+		"component type must not be empty"
+	);
+	let component = Component {
+		address,
+		type_name: component_type.to_owned(),
+		methods: methods
+			.iter()
+			.map(|&(name, attributes, result)| {
+				let result = result.to_vec();
+				Method {
+					name: name.to_owned(),
+					attributes,
+					handler: Rc::new(move |_| Ok(result.clone())),
+				}
+			})
+			.collect(),
+	};
+	with_state(|state| {
+		state.components.retain(|i| i.address != address);
+		state.components.push(component);
+	});
+}
+
+/// Adds a method to a registered component, or replaces an existing method of the same name.
+///
+/// The `address` parameter identifies the component, and `method` is the method name. The
+/// `attributes` parameter determines how calls behave: a call to a direct method runs `handler`
+/// immediately and completes within the same timeslice, while a call to an indirect method is
+/// incomplete when started, returns [`Pending`](crate::component::InvokeEndResult::Pending) from
+/// the first attempt to fetch its result (during which `handler` runs, simulating the next
+/// timeslice), and is done on the second attempt.
+///
+/// The `handler` parameter is called with the CBOR-encoded parameters (or an empty slice if none
+/// were passed) and returns either the CBOR-encoded result or the error with which the call fails.
+/// For [`BadParameters`](Error::BadParameters) and [`Other`](Error::Other), the error message is
+/// available via [`LastException`](crate::component::LastException). The handler must not panic,
+/// because it runs inside a system call, and unwinding out of a system call aborts the process.
+///
+/// # Example
+/// ```
+/// use oc_wasm_safe::component::{InvokeEndResult, InvokeResult, Invoker, MethodAttributes};
+/// use oc_wasm_safe::{mock, Address};
+///
+/// let address = Address::from_bytes([2; 16]);
+/// mock::register_component(address, "example", &[]);
+/// let direct = MethodAttributes {
+///     direct: true,
+///     getter: false,
+///     setter: false,
+/// };
+/// let indirect = MethodAttributes {
+///     direct: false,
+///     ..direct
+/// };
+/// // Both methods return their parameters unchanged.
+/// mock::set_method(&address, "echo", direct, |params| Ok(params.to_vec()));
+/// mock::set_method(&address, "slow_echo", indirect, |params| Ok(params.to_vec()));
+///
+/// let mut invoker = Invoker::take().unwrap();
+/// let mut buffer = [0_u8; 16];
+/// let params = [0x81, 0x01];
+///
+/// let (result, call) = invoker.component_method(&address, "echo", Some(&params)).unwrap();
+/// assert_eq!(result, InvokeResult::Complete);
+/// let len = call.end(&mut buffer).expect_done().unwrap();
+/// assert_eq!(&buffer[..len], &params);
+///
+/// let (result, call) = invoker.component_method(&address, "slow_echo", Some(&params)).unwrap();
+/// assert_eq!(result, InvokeResult::Incomplete);
+/// let InvokeEndResult::Pending(call) = call.end(&mut buffer) else {
+///     panic!("expected Pending");
+/// };
+/// let len = call.end(&mut buffer).expect_done().unwrap();
+/// assert_eq!(&buffer[..len], &params);
+/// ```
+///
+/// # Panics
+/// This function panics if no component with the given address is registered.
+pub fn set_method(
+	address: &Address,
+	method: &str,
+	attributes: MethodAttributes,
+	handler: impl Fn(&[u8]) -> Result<Vec<u8>, Error> + 'static,
+) {
+	let method = Method {
+		name: method.to_owned(),
+		attributes,
+		handler: Rc::new(handler),
+	};
+	with_state(|state| {
+		let component = state
+			.components
+			.iter_mut()
+			.find(|i| i.address == *address)
+			.expect("component must be registered before adding methods");
+		component.methods.retain(|i| i.name != method.name);
+		component.methods.push(method);
+	});
+}
+
+/// Removes a component, as if it had been detached from the computer.
+///
+/// If no component with the given address is registered, nothing happens.
+pub fn unregister_component(address: &Address) {
+	with_state(|state| state.components.retain(|i| i.address != *address));
+}
+
+/// Opens a new descriptor and returns its raw value.
+///
+/// The descriptor can be embedded in a method result (as an integer tagged with the Identifier
+/// tag) to simulate a method returning an opaque value.
+#[must_use = "The descriptor will leak if not returned to the code under test"]
+pub fn new_descriptor() -> u32 {
+	with_state(State::new_descriptor)
+}
+
+/// Returns the number of descriptors that are currently open.
+///
+/// This is useful for checking that code under test does not leak descriptors.
+#[must_use = "This function is only useful for its return value"]
+pub fn open_descriptors() -> usize {
+	with_state(|state| state.descriptors.len())
+}
+
+/// Returns the number of signals waiting in the signal queue.
+#[must_use = "This function is only useful for its return value"]
+pub fn pending_signals() -> usize {
+	with_state(|state| state.signals.len())
+}
+
+/// Adds a signal to the end of the signal queue.
+///
+/// The `signal` parameter is the signal data in the form returned by
+/// [`pull_signal`](crate::computer::pull_signal): a CBOR sequence of the signal name followed by
+/// an array of parameters. It is queued verbatim, without validation.
+///
+/// # Errors
+/// * [`QueueFull`](Error::QueueFull) is returned if the queue already holds
+///   [`signal_queue_capacity`](Computer::signal_queue_capacity) signals.
+///
+/// # Example
+/// ```
+/// use oc_wasm_safe::{computer, mock};
+///
+/// // Each signal is the name as a CBOR string followed by an empty CBOR array of parameters.
+/// mock::push_signal_raw(&[0x61, b'a', 0x80]).unwrap();
+/// mock::push_signal_raw(&[0x61, b'b', 0x80]).unwrap();
+/// assert_eq!(computer::pull_signal_length().unwrap().get(), 3);
+///
+/// let mut buffer = [0_u8; 16];
+/// assert_eq!(computer::pull_signal(&mut buffer).unwrap().unwrap(), &[0x61, b'a', 0x80]);
+/// assert_eq!(computer::pull_signal(&mut buffer).unwrap().unwrap(), &[0x61, b'b', 0x80]);
+/// assert!(computer::pull_signal_length().is_none());
+/// ```
+pub fn push_signal_raw(signal: &[u8]) -> Result<(), Error> {
+	with_state(|state| state.push_signal(signal.to_vec()))
+}
+
+/// Discards all signals waiting in the signal queue.
+pub fn clear_signals() {
+	with_state(|state| state.signals.clear());
+}
+
+/// Runs a function with access to the properties of the simulated computer.
+///
+/// This can be used to examine or change values such as the installed RAM or uptime.
+pub fn computer<R>(f: impl FnOnce(&mut Computer) -> R) -> R {
+	with_state(|state| f(&mut state.computer))
+}
+
+/// Makes the next call to a system call fail.
+///
+/// The `syscall` parameter names the system call by its path within `oc_wasm_sys`, such as
+/// `"component::list_next"`, and `code` is the value that call returns instead of doing anything
+/// else. This makes it possible to exercise error paths that a real OC-Wasm should never take.
+/// Only the next call is affected; calling this function again for the same system call before
+/// then replaces the code.
+///
+/// # Example
+/// ```
+/// use oc_wasm_safe::component::Lister;
+/// use oc_wasm_safe::{mock, Address};
+///
+/// mock::register_component(Address::from_bytes([3; 16]), "example", &[]);
+/// let mut lister = Lister::take().unwrap();
+/// let mut listing = lister.start(None);
+/// mock::fail_next("component::list_next", -12);
+/// assert!(listing.try_next().is_err());
+/// assert!(listing.try_next().unwrap().is_some());
+/// ```
+///
+/// # Panics
+/// This function panics if `syscall` does not name a system call that returns an integer.
+pub fn fail_next(syscall: &str, code: i32) {
+	let syscall = intercepted(syscall);
+	with_state(|state| state.failures.insert(syscall, code));
+}
+
+/// Returns the number of calls made to a system call since the thread started or the simulated
+/// computer was last [`reset`](reset).
+///
+/// The `syscall` parameter names the system call in the same way as for
+/// [`fail_next`](fail_next). Calls that failed because of [`fail_next`](fail_next) are included.
+///
+/// # Panics
+/// This function panics if `syscall` does not name a system call that returns an integer.
+#[must_use = "This function is only useful for its return value"]
+pub fn syscall_count(syscall: &str) -> usize {
+	let syscall = intercepted(syscall);
+	with_state(|state| state.syscall_counts.get(syscall).copied().unwrap_or(0))
+}
+
+/// Returns the entry in [`INTERCEPTED`](INTERCEPTED) for a system call name.
+///
+/// # Panics
+/// This function panics if there is no such entry.
+fn intercepted(syscall: &str) -> &'static str {
+	INTERCEPTED
+		.iter()
+		.find(|&&i| i == syscall)
+		.unwrap_or_else(|| panic!("{syscall} is not an intercepted system call"))
+}
+
+/// Records a call to a system call and returns the error code it should fail with, if any.
+fn intercept(syscall: &'static str) -> Option<i32> {
+	with_state(|state| {
+		*state.syscall_counts.entry(syscall).or_default() += 1;
+		state.failures.remove(syscall)
+	})
+}
+
+impl State {
+	/// Allocates the lowest descriptor that is not already open.
+	fn new_descriptor(&mut self) -> u32 {
+		let descriptor = (0..=u32::MAX)
+			.find(|i| !self.descriptors.contains(i))
+			.unwrap_or(u32::MAX);
+		self.descriptors.insert(descriptor);
+		descriptor
+	}
+
+	/// Adds a signal, in the form returned by `pull_signal`, to the end of the signal queue.
+	fn push_signal(&mut self, signal: Vec<u8>) -> Result<(), Error> {
+		if self.signals.len() >= self.computer.signal_queue_capacity {
+			Err(Error::QueueFull)
+		} else {
+			self.signals.push_back(signal);
+			Ok(())
+		}
+	}
+
+	/// Returns the component with a given address.
+	fn component(&self, address: &Address) -> Option<&Component> {
+		self.components.iter().find(|i| i.address == *address)
+	}
+
+	/// Records the result of a method call that has finished running.
+	///
+	/// If the call failed, the error description becomes the exception message.
+	fn finish_call(&mut self, result: Result<Vec<u8>, Error>) -> i32 {
+		if let Err(e) = result {
+			e.as_str().clone_into(&mut self.exception);
+		}
+		self.call = Some(Call::Done(result));
+		1
+	}
+
+	/// Records the result of a method call that failed with an exception.
+	fn fail_call(&mut self, error: Error, message: &str) -> i32 {
+		self.call = Some(Call::Done(Err(error)));
+		message.clone_into(&mut self.exception);
+		1
+	}
+}
+
+/// Converts method attributes to the bitmask used by `methods_next`.
+fn attributes_to_bits(attributes: MethodAttributes) -> u32 {
+	u32::from(attributes.direct)
+		| u32::from(attributes.getter) << 1
+		| u32::from(attributes.setter) << 2
+}
+
+/// Converts a length to a system call return value.
+fn length(len: usize) -> isize {
+	isize::try_from(len).unwrap_or(isize::MAX)
+}
+
+/// Converts an error code to a system call return value of type `isize`.
+fn code(code: i32) -> isize {
+	isize::try_from(code).unwrap_or(isize::MIN)
+}
+
+/// Reads a CBOR data item from a pointer without an accompanying length.
+///
+/// # Errors
+/// [`CborDecode`](Error::CborDecode) is returned if the data is not a valid CBOR data item.
+///
+/// # Safety
+/// `ptr` must point to a valid CBOR data item, or to readable memory containing at least enough
+/// bytes for the decoder to detect that the data is invalid.
+unsafe fn read_cbor<'a>(ptr: *const u8) -> Result<&'a [u8], Error> {
+	// Grow the slice one byte at a time until it holds exactly one complete item, so that no byte
+	// past the end of the item is ever read.
+	let mut len = 1;
+	loop {
+		let data = core::slice::from_raw_parts(ptr, len);
+		match Decoder::new(data).skip() {
+			Ok(()) => return Ok(data),
+			Err(e) if e.is_end_of_input() => len += 1,
+			Err(_) => return Err(Error::CborDecode),
+		}
+	}
+}
+
+/// Reads an optional CBOR data item, which is absent if the pointer is null.
+///
+/// # Errors
+/// [`CborDecode`](Error::CborDecode) is returned if the data is not a valid CBOR data item.
+///
+/// # Safety
+/// `ptr` must be null or satisfy the requirements of [`read_cbor`](read_cbor).
+unsafe fn read_params<'a>(ptr: *const u8) -> Result<&'a [u8], Error> {
+	if ptr.is_null() {
+		Ok(&[])
+	} else {
+		read_cbor(ptr)
+	}
+}
+
+/// Reads a string from a pointer/length pair.
+///
+/// # Errors
+/// The `StringDecode` error code is returned if the data is not valid UTF-8.
+///
+/// # Safety
+/// `ptr` and `len` must identify readable memory.
+unsafe fn read_str<'a>(ptr: *const u8, len: usize) -> Result<&'a str, i32> {
+	core::str::from_utf8(core::slice::from_raw_parts(ptr, len)).map_err(|_| STRING_DECODE)
+}
+
+/// Reads a component address from a pointer.
+///
+/// # Safety
+/// `ptr` must point to 16 readable bytes.
+unsafe fn read_address(ptr: *const u8) -> Address {
+	Address::from_bytes(ptr.cast::<[u8; 16]>().read_unaligned())
+}
+
+/// Writes data to a buffer in the manner of a buffer-writing system call.
+///
+/// If `ptr` is null, the length of `data` is returned without writing anything. If the buffer is
+/// too short, [`BufferTooShort`](Error::BufferTooShort) is returned. Otherwise, `data` is written
+/// and its length is returned.
+///
+/// # Safety
+/// `ptr` must be null or point to `len` writeable bytes.
+unsafe fn write_buffer(data: &[u8], ptr: *mut u8, len: usize) -> isize {
+	if ptr.is_null() {
+		length(data.len())
+	} else if len < data.len() {
+		code(Error::BufferTooShort.code())
+	} else {
+		core::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+		length(data.len())
+	}
+}
+
+/// Replacements for the system calls in `oc_wasm_sys`.
+///
+/// Each function has the same signature and contract as its counterpart in `oc_wasm_sys`.
+#[allow(clippy::missing_safety_doc)] // The safety requirements are those of oc_wasm_sys.
+pub(crate) mod sys {
+	pub mod component {
+		use super::super::{
+			attributes_to_bits, code, intercept, read_address, read_params, read_str, with_state,
+			write_buffer, Call, Error,
+		};
+		use std::rc::Rc;
+
+		pub unsafe extern "C" fn list_start(ptr: *const u8, len: usize) -> i32 {
+			if let Some(e) = intercept("component::list_start") {
+				return e;
+			}
+			let component_type = if ptr.is_null() {
+				None
+			} else {
+				match read_str(ptr, len) {
+					Ok(s) => Some(s),
+					Err(e) => return e,
+				}
+			};
+			with_state(|state| {
+				state.listing = state
+					.components
+					.iter()
+					.filter(|i| match component_type {
+						Some(t) => i.type_name == t,
+						None => true,
+					})
+					.map(|i| (i.address, i.type_name.clone()))
+					.collect();
+				state.listed_type = None;
+			});
+			0
+		}
+
+		pub unsafe extern "C" fn list_next(ptr: *mut u8) -> i32 {
+			if let Some(e) = intercept("component::list_next") {
+				return e;
+			}
+			with_state(|state| {
+				if let Some((address, component_type)) = state.listing.pop_front() {
+					ptr.cast::<[u8; 16]>().write_unaligned(*address.as_bytes());
+					state.listed_type = Some(component_type);
+					1
+				} else {
+					state.listed_type = None;
+					0
+				}
+			})
+		}
+
+		pub unsafe extern "C" fn list_type(ptr: *mut u8, len: usize) -> isize {
+			if let Some(e) = intercept("component::list_type") {
+				return code(e);
+			}
+			with_state(|state| match &state.listed_type {
+				Some(component_type) => write_buffer(component_type.as_bytes(), ptr, len),
+				None => code(Error::Other.code()),
+			})
+		}
+
+		pub unsafe extern "C" fn component_type(
+			address: *const u8,
+			ptr: *mut u8,
+			len: usize,
+		) -> isize {
+			if let Some(e) = intercept("component::component_type") {
+				return code(e);
+			}
+			let address = read_address(address);
+			with_state(|state| match state.component(&address) {
+				Some(component) => write_buffer(component.type_name.as_bytes(), ptr, len),
+				None => code(Error::NoSuchComponent.code()),
+			})
+		}
+
+		pub unsafe extern "C" fn slot(address: *const u8, _len: usize) -> i32 {
+			if let Some(e) = intercept("component::slot") {
+				return e;
+			}
+			let address = read_address(address);
+			with_state(|state| {
+				if state.component(&address).is_some() {
+					// Simulated components are never installed in slots.
+					Error::Other.code()
+				} else {
+					Error::NoSuchComponent.code()
+				}
+			})
+		}
+
+		pub unsafe extern "C" fn methods_start_component(address: *const u8) -> i32 {
+			if let Some(e) = intercept("component::methods_start_component") {
+				return e;
+			}
+			let address = read_address(address);
+			with_state(|state| {
+				let methods = match state.component(&address) {
+					Some(component) => component
+						.methods
+						.iter()
+						.map(|i| (i.name.clone(), attributes_to_bits(i.attributes)))
+						.collect(),
+					None => return Error::NoSuchComponent.code(),
+				};
+				state.method_listing = methods;
+				0
+			})
+		}
+
+		pub unsafe extern "C" fn methods_start_value(descriptor: u32) -> i32 {
+			if let Some(e) = intercept("component::methods_start_value") {
+				return e;
+			}
+			with_state(|state| {
+				if state.descriptors.contains(&descriptor) {
+					// Simulated opaque values have no methods.
+					state.method_listing.clear();
+					0
+				} else {
+					Error::BadDescriptor.code()
+				}
+			})
+		}
+
+		pub unsafe extern "C" fn methods_next(
+			ptr: *mut u8,
+			len: usize,
+			attributes: *mut u32,
+		) -> isize {
+			if let Some(e) = intercept("component::methods_next") {
+				return code(e);
+			}
+			with_state(|state| {
+				let Some((name, bits)) = state.method_listing.front() else {
+					return 0;
+				};
+				let ret = write_buffer(name.as_bytes(), ptr, len);
+				if !ptr.is_null() && ret >= 0 {
+					if !attributes.is_null() {
+						attributes.write(*bits);
+					}
+					state.method_listing.pop_front();
+				}
+				ret
+			})
+		}
+
+		pub unsafe extern "C" fn documentation_component(
+			address: *const u8,
+			method: *const u8,
+			method_len: usize,
+			ptr: *mut u8,
+			len: usize,
+		) -> isize {
+			if let Some(e) = intercept("component::documentation_component") {
+				return code(e);
+			}
+			let address = read_address(address);
+			let method = match read_str(method, method_len) {
+				Ok(method) => method,
+				Err(e) => return code(e),
+			};
+			with_state(|state| match state.component(&address) {
+				Some(component) if component.methods.iter().any(|i| i.name == method) => {
+					// Simulated methods have no documentation.
+					write_buffer(&[], ptr, len)
+				}
+				Some(_) => code(Error::NoSuchMethod.code()),
+				None => code(Error::NoSuchComponent.code()),
+			})
+		}
+
+		pub unsafe extern "C" fn documentation_value(
+			_descriptor: u32,
+			method: *const u8,
+			method_len: usize,
+			_ptr: *mut u8,
+			_len: usize,
+		) -> isize {
+			if let Some(e) = intercept("component::documentation_value") {
+				return code(e);
+			}
+			match read_str(method, method_len) {
+				// Simulated opaque values have no methods.
+				Ok(_) => code(Error::NoSuchMethod.code()),
+				Err(e) => code(e),
+			}
+		}
+
+		pub unsafe extern "C" fn invoke_component_method(
+			address: *const u8,
+			method: *const u8,
+			method_len: usize,
+			params: *const u8,
+		) -> i32 {
+			if let Some(e) = intercept("component::invoke_component_method") {
+				return e;
+			}
+			let address = read_address(address);
+			let method = match read_str(method, method_len) {
+				Ok(method) => method,
+				Err(e) => return e,
+			};
+			let params = match read_params(params) {
+				Ok(params) => params,
+				Err(e) => return e.code(),
+			};
+			let method = with_state(|state| match state.component(&address) {
+				Some(component) => match component.methods.iter().find(|i| i.name == method) {
+					Some(method) => Ok((Rc::clone(&method.handler), method.attributes.direct)),
+					None => Err(Error::NoSuchMethod),
+				},
+				None => Err(Error::NoSuchComponent),
+			});
+			match method {
+				Ok((handler, true)) => {
+					// The handler may itself use the mock, so it must run without the state
+					// borrowed.
+					let result = handler(params);
+					with_state(|state| state.finish_call(result))
+				}
+				Ok((handler, false)) => {
+					with_state(|state| state.call = Some(Call::Pending(handler, params.to_vec())));
+					0
+				}
+				Err(e) => with_state(|state| state.finish_call(Err(e))),
+			}
+		}
+
+		pub unsafe extern "C" fn invoke_value(_descriptor: u32, params: *const u8) -> i32 {
+			if let Some(e) = intercept("component::invoke_value") {
+				return e;
+			}
+			if let Err(e) = read_params(params) {
+				return e.code();
+			}
+			with_state(|state| state.fail_call(Error::Other, "value is not callable"))
+		}
+
+		pub unsafe extern "C" fn invoke_value_indexed_read(
+			_descriptor: u32,
+			params: *const u8,
+		) -> i32 {
+			if let Some(e) = intercept("component::invoke_value_indexed_read") {
+				return e;
+			}
+			if let Err(e) = read_params(params) {
+				return e.code();
+			}
+			with_state(|state| state.fail_call(Error::Other, "value is not indexable"))
+		}
+
+		pub unsafe extern "C" fn invoke_value_indexed_write(
+			_descriptor: u32,
+			params: *const u8,
+		) -> i32 {
+			if let Some(e) = intercept("component::invoke_value_indexed_write") {
+				return e;
+			}
+			if let Err(e) = read_params(params) {
+				return e.code();
+			}
+			with_state(|state| state.fail_call(Error::Other, "value is not indexable"))
+		}
+
+		pub unsafe extern "C" fn invoke_value_method(
+			_descriptor: u32,
+			method: *const u8,
+			method_len: usize,
+			params: *const u8,
+		) -> i32 {
+			if let Some(e) = intercept("component::invoke_value_method") {
+				return e;
+			}
+			if let Err(e) = read_str(method, method_len) {
+				return e;
+			}
+			if let Err(e) = read_params(params) {
+				return e.code();
+			}
+			// Simulated opaque values have no methods.
+			with_state(|state| state.finish_call(Err(Error::NoSuchMethod)))
+		}
+
+		pub unsafe extern "C" fn invoke_end(ptr: *mut u8, len: usize) -> isize {
+			if let Some(e) = intercept("component::invoke_end") {
+				return code(e);
+			}
+			let pending = with_state(|state| match state.call.take() {
+				Some(Call::Pending(handler, params)) => Some((handler, params)),
+				other => {
+					state.call = other;
+					None
+				}
+			});
+			if let Some((handler, params)) = pending {
+				// Simulate the next timeslice, in which the indirect method runs. The handler may
+				// itself use the mock, so it must run without the state borrowed.
+				let result = handler(&params);
+				with_state(|state| state.finish_call(result));
+				return code(Error::QueueEmpty.code());
+			}
+			with_state(|state| match &state.call {
+				Some(Call::Done(Ok(result))) => {
+					let ret = write_buffer(result, ptr, len);
+					if !ptr.is_null() && ret >= 0 {
+						state.call = None;
+					}
+					ret
+				}
+				Some(Call::Done(Err(e))) => {
+					let ret = code(e.code());
+					state.call = None;
+					ret
+				}
+				Some(Call::Pending(..)) | None => code(Error::Other.code()),
+			})
+		}
+
+		pub unsafe extern "C" fn invoke_cancel() {
+			with_state(|state| state.call = None);
+		}
+
+		pub unsafe extern "C" fn last_exception_message(ptr: *mut u8, len: usize) -> isize {
+			if let Some(e) = intercept("component::last_exception_message") {
+				return code(e);
+			}
+			with_state(|state| write_buffer(state.exception.as_bytes(), ptr, len))
+		}
+
+		pub unsafe extern "C" fn last_exception_is_type(ptr: *const u8, len: usize) -> i32 {
+			if let Some(e) = intercept("component::last_exception_is_type") {
+				return e;
+			}
+			match read_str(ptr, len) {
+				// Simulated exceptions do not have Java classes.
+				Ok(_) => 0,
+				Err(e) => e,
+			}
+		}
+	}
+
+	pub mod computer {
+		use super::super::{code, intercept, read_cbor, read_str, with_state, write_buffer, Error};
+		use minicbor::decode::Decoder;
+		use minicbor::encode::{write::Cursor, Encoder};
+		use ordered_float::NotNan;
+
+		pub unsafe extern "C" fn uptime() -> NotNan<f64> {
+			with_state(|state| state.computer.uptime)
+		}
+
+		pub unsafe extern "C" fn cpu_time() -> NotNan<f64> {
+			with_state(|state| state.computer.cpu_time)
+		}
+
+		pub unsafe extern "C" fn world_time() -> u64 {
+			with_state(|state| state.computer.world_time)
+		}
+
+		pub unsafe extern "C" fn address(ptr: *mut u8) -> i32 {
+			if let Some(e) = intercept("computer::address") {
+				return e;
+			}
+			let address = with_state(|state| state.computer.address);
+			ptr.cast::<[u8; 16]>().write_unaligned(*address.as_bytes());
+			0
+		}
+
+		pub unsafe extern "C" fn tmpfs_address(ptr: *mut u8) -> i32 {
+			if let Some(e) = intercept("computer::tmpfs_address") {
+				return e;
+			}
+			let address = with_state(|state| state.computer.tmpfs_address);
+			ptr.cast::<[u8; 16]>().write_unaligned(*address.as_bytes());
+			0
+		}
+
+		pub unsafe extern "C" fn installed_ram() -> u32 {
+			with_state(|state| state.computer.installed_ram)
+		}
+
+		pub unsafe extern "C" fn free_ram() -> u32 {
+			with_state(|state| state.computer.free_ram)
+		}
+
+		pub unsafe extern "C" fn push_signal(ptr: *const u8) -> i32 {
+			if let Some(e) = intercept("computer::push_signal") {
+				return e;
+			}
+			// Convert the array of name and parameters into a name followed by an array of
+			// parameters, which is the form returned by pull_signal.
+			let Ok(signal) = read_cbor(ptr) else {
+				return Error::CborDecode.code();
+			};
+			let mut decoder = Decoder::new(signal);
+			let Ok(Some(count @ 1..)) = decoder.array() else {
+				return Error::CborDecode.code();
+			};
+			let name_start = decoder.position();
+			if decoder.str().is_err() {
+				return Error::CborDecode.code();
+			}
+			let name = &signal[name_start..decoder.position()];
+			let params = &signal[decoder.position()..];
+			let mut header = Encoder::new(Cursor::new([0_u8; 9]));
+			if header.array(count - 1).is_err() {
+				return Error::CborDecode.code();
+			}
+			let header = header.into_writer();
+			let header = &header.get_ref()[..header.position()];
+			let signal = [name, header, params].concat();
+			match with_state(|state| state.push_signal(signal)) {
+				Ok(()) => 0,
+				Err(e) => e.code(),
+			}
+		}
+
+		pub unsafe extern "C" fn pull_signal(ptr: *mut u8, len: usize) -> isize {
+			if let Some(e) = intercept("computer::pull_signal") {
+				return code(e);
+			}
+			with_state(|state| {
+				let Some(signal) = state.signals.front() else {
+					return 0;
+				};
+				let ret = write_buffer(signal, ptr, len);
+				if !ptr.is_null() && ret >= 0 {
+					state.signals.pop_front();
+				}
+				ret
+			})
+		}
+
+		pub unsafe extern "C" fn acl_start() {
+			with_state(|state| state.users_position = 0);
+		}
+
+		pub unsafe extern "C" fn acl_next(ptr: *mut u8, len: usize) -> isize {
+			if let Some(e) = intercept("computer::acl_next") {
+				return code(e);
+			}
+			with_state(|state| {
+				let Some(user) = state.users.get(state.users_position) else {
+					return 0;
+				};
+				let ret = write_buffer(user.as_bytes(), ptr, len);
+				if !ptr.is_null() && ret >= 0 {
+					state.users_position += 1;
+				}
+				ret
+			})
+		}
+
+		pub unsafe extern "C" fn add_user(ptr: *const u8, len: usize) -> i32 {
+			if let Some(e) = intercept("computer::add_user") {
+				return e;
+			}
+			let name = match read_str(ptr, len) {
+				Ok(name) => name,
+				Err(e) => return e,
+			};
+			with_state(|state| {
+				if state.users.iter().any(|i| i == name) {
+					Error::Other.code()
+				} else {
+					state.users.push(name.to_owned());
+					0
+				}
+			})
+		}
+
+		pub unsafe extern "C" fn remove_user(ptr: *const u8, len: usize) -> i32 {
+			if let Some(e) = intercept("computer::remove_user") {
+				return e;
+			}
+			let name = match read_str(ptr, len) {
+				Ok(name) => name,
+				Err(e) => return e,
+			};
+			with_state(|state| {
+				if let Some(index) = state.users.iter().position(|i| i == name) {
+					state.users.remove(index);
+					0
+				} else {
+					Error::Other.code()
+				}
+			})
+		}
+
+		pub unsafe extern "C" fn energy() -> NotNan<f64> {
+			with_state(|state| state.computer.energy)
+		}
+
+		pub unsafe extern "C" fn max_energy() -> NotNan<f64> {
+			with_state(|state| state.computer.max_energy)
+		}
+
+		pub unsafe extern "C" fn char_width(ch: u32) -> u32 {
+			// The simulated terminal treats the main blocks of East Asian wide characters as two
+			// columns and every other character as one.
+			match ch {
+				0x1100..=0x115F
+				| 0x2E80..=0xA4CF
+				| 0xAC00..=0xD7A3
+				| 0xF900..=0xFAFF
+				| 0xFE30..=0xFE4F
+				| 0xFF00..=0xFF60
+				| 0xFFE0..=0xFFE6
+				| 0x2_0000..=0x3_FFFD => 2,
+				_ => 1,
+			}
+		}
+
+		pub unsafe extern "C" fn beep(_frequency: i32, _duration: i32) {}
+
+		pub unsafe extern "C" fn beep_pattern(ptr: *const u8, len: usize) -> i32 {
+			if let Some(e) = intercept("computer::beep_pattern") {
+				return e;
+			}
+			match read_str(ptr, len) {
+				Ok(_) => 0,
+				Err(e) => e,
+			}
+		}
+
+		pub unsafe fn shutdown() -> ! {
+			panic!("computer shut down")
+		}
+
+		pub unsafe fn reboot() -> ! {
+			panic!("computer rebooted")
+		}
+
+		pub unsafe fn error(ptr: *const u8, len: usize) -> ! {
+			let message = read_str(ptr, len).unwrap_or("<invalid UTF-8>");
+			panic!("computer halted with error: {message}")
+		}
+
+		pub unsafe extern "C" fn debug(ptr: *const u8, len: usize) -> i32 {
+			if let Some(e) = intercept("computer::debug") {
+				return e;
+			}
+			match read_str(ptr, len) {
+				Ok(_) => 0,
+				Err(e) => e,
+			}
+		}
+	}
+
+	pub mod descriptor {
+		use super::super::{intercept, with_state, Error};
+
+		pub unsafe extern "C" fn close(descriptor: u32) -> i32 {
+			if let Some(e) = intercept("descriptor::close") {
+				return e;
+			}
+			with_state(|state| {
+				if state.descriptors.remove(&descriptor) {
+					0
+				} else {
+					Error::BadDescriptor.code()
+				}
+			})
+		}
+
+		pub unsafe extern "C" fn dup(descriptor: u32) -> i32 {
+			if let Some(e) = intercept("descriptor::dup") {
+				return e;
+			}
+			with_state(|state| {
+				if !state.descriptors.contains(&descriptor) {
+					Error::BadDescriptor.code()
+				} else if state.descriptors.len() >= state.computer.descriptor_limit {
+					Error::TooManyDescriptors.code()
+				} else {
+					i32::try_from(state.new_descriptor()).unwrap_or(i32::MAX)
+				}
+			})
+		}
+	}
+
+	pub mod execute {
+		use super::super::{intercept, with_state, Error};
+
+		pub unsafe extern "C" fn clear() {
+			with_state(|state| state.execute_len = 0);
+		}
+
+		pub unsafe extern "C" fn add(_ptr: *const u8, len: usize) -> i32 {
+			if let Some(e) = intercept("execute::add") {
+				return e;
+			}
+			with_state(|state| {
+				let new_len = state.execute_len.saturating_add(len);
+				if new_len > state.computer.installed_ram as usize {
+					Error::Other.code()
+				} else {
+					state.execute_len = new_len;
+					0
+				}
+			})
+		}
+
+		pub unsafe fn execute() -> ! {
+			panic!("execute called")
+		}
+	}
+}
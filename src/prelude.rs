@@ -0,0 +1,10 @@
+//! Re-exports of the most commonly used types and traits.
+//!
+//! A typical program can import everything it needs with `use oc_wasm_safe::prelude::*;`.
+
+pub use super::component::{
+	Component, Environment, InvokeResult, Invoker, Lister, MethodCall, MethodLister,
+};
+pub use super::descriptor::{AsDescriptor, AsRaw, Borrowed, IntoDescriptor, Owned};
+pub use super::error::{Error, Result, ResultExt};
+pub use super::Address;
@@ -0,0 +1,175 @@
+//! A reusable buffer for system calls that write into caller-provided memory.
+//!
+//! Many functions in this crate, such as
+//! [`ListEntry::type_name`](crate::component::ListEntry::type_name),
+//! [`MethodListing::next`](crate::component::MethodListing::next), and
+//! [`MethodCall::end`](crate::component::MethodCall::end), take a `&mut [u8]` to write their
+//! output into. A program with a tight memory budget can keep a single
+//! [`ScratchBuffer`](ScratchBuffer) and pass it to all of them, rather than declaring a separate
+//! array for each call.
+
+use core::ops::{Deref, DerefMut};
+
+/// A fixed-size buffer that can be reused across calls.
+///
+/// A value of this type dereferences to its entire backing storage as a `[u8]`, so it can be
+/// passed anywhere a `&mut [u8]` is expected. Alternatively, [`fill`](ScratchBuffer::fill) can be
+/// used to run a call against the storage and remember how many bytes it wrote, after which the
+/// written bytes are available from [`written`](ScratchBuffer::written) until the next `fill` or
+/// a call to [`clear`](ScratchBuffer::clear).
+///
+/// Dereferencing does not forget the written bytes, so they can be inspected or edited in place
+/// through the `[u8]` as well. To hand the storage to a call that does not go through `fill`, use
+/// [`as_write_buffer`](ScratchBuffer::as_write_buffer), which forgets them first.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ScratchBuffer<const N: usize> {
+	/// The backing storage.
+	data: [u8; N],
+
+	/// The number of bytes written by the most recent call to `fill`.
+	len: usize,
+}
+
+impl<const N: usize> ScratchBuffer<N> {
+	/// Creates a new, zero-filled buffer.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn new() -> Self {
+		Self {
+			data: [0; N],
+			len: 0,
+		}
+	}
+
+	/// Returns the size of the backing storage.
+	#[must_use = "This function is only useful for its return value"]
+	pub const fn capacity(&self) -> usize {
+		N
+	}
+
+	/// Writes into the buffer and records how many bytes were written.
+	///
+	/// The `f` parameter is called with the entire backing storage and must return the number of
+	/// bytes it wrote at the start of it. On success, a slice over those bytes is returned.
+	///
+	/// # Errors
+	/// Any error returned by `f` is returned by this function, in which case the buffer is
+	/// considered empty.
+	///
+	/// # Panics
+	/// This function panics if `f` returns a length larger than the buffer.
+	pub fn fill<E>(
+		&mut self,
+		f: impl FnOnce(&mut [u8]) -> Result<usize, E>,
+	) -> Result<&mut [u8], E> {
+		self.len = 0;
+		let len = f(&mut self.data)?;
+		self.len = len;
+		Ok(&mut self.data[..len])
+	}
+
+	/// Returns the bytes written by the most recent call to [`fill`](ScratchBuffer::fill).
+	///
+	/// If `fill` has not been called, the most recent call failed, or the buffer has been cleared
+	/// since, an empty slice is returned.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn written(&self) -> &[u8] {
+		&self.data[..self.len]
+	}
+
+	/// Forgets the bytes written by the most recent call to [`fill`](ScratchBuffer::fill).
+	///
+	/// The backing storage is left as it is; only [`written`](ScratchBuffer::written) is affected.
+	pub fn clear(&mut self) {
+		self.len = 0;
+	}
+
+	/// Clears the buffer and returns its entire backing storage for writing.
+	///
+	/// This is for passing the buffer to a call directly, rather than through
+	/// [`fill`](ScratchBuffer::fill), when whatever the call writes should not be mistaken for the
+	/// bytes written by an earlier `fill`.
+	#[must_use = "This function is only useful for its return value"]
+	pub fn as_write_buffer(&mut self) -> &mut [u8] {
+		self.clear();
+		&mut self.data
+	}
+}
+
+impl<const N: usize> Default for ScratchBuffer<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const N: usize> Deref for ScratchBuffer<N> {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		&self.data
+	}
+}
+
+impl<const N: usize> DerefMut for ScratchBuffer<N> {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		&mut self.data
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fill_records_len() {
+		let mut buffer = ScratchBuffer::<4>::new();
+		assert_eq!(buffer.written(), b"");
+		let filled = buffer
+			.fill(|data| {
+				data[..2].copy_from_slice(b"ab");
+				Ok::<_, ()>(2)
+			})
+			.unwrap();
+		assert_eq!(filled, b"ab");
+		assert_eq!(buffer.written(), b"ab");
+		assert_eq!(buffer.len(), 4);
+	}
+
+	#[test]
+	fn failed_fill_is_empty() {
+		let mut buffer = ScratchBuffer::<4>::new();
+		buffer.fill(|_| Ok::<_, ()>(3)).unwrap();
+		assert_eq!(buffer.fill(|_| Err(())), Err(()));
+		assert_eq!(buffer.written(), b"");
+	}
+
+	#[test]
+	#[should_panic]
+	fn fill_too_long() {
+		let mut buffer = ScratchBuffer::<4>::new();
+		let _ = buffer.fill(|_| Ok::<_, ()>(5));
+	}
+
+	#[test]
+	fn deref_mut_keeps_written() {
+		let mut buffer = ScratchBuffer::<4>::new();
+		buffer
+			.fill(|data| {
+				data[..3].copy_from_slice(b"abc");
+				Ok::<_, ()>(3)
+			})
+			.unwrap();
+		buffer[0] = b'x';
+		assert_eq!(buffer.written(), b"xbc");
+	}
+
+	#[test]
+	fn clear_and_as_write_buffer_forget_written() {
+		let mut buffer = ScratchBuffer::<4>::new();
+		buffer.fill(|_| Ok::<_, ()>(2)).unwrap();
+		buffer.clear();
+		assert_eq!(buffer.written(), b"");
+		buffer.fill(|_| Ok::<_, ()>(2)).unwrap();
+		assert_eq!(buffer.as_write_buffer().len(), 4);
+		assert_eq!(buffer.written(), b"");
+	}
+}
@@ -0,0 +1,269 @@
+//! A dynamically typed representation of CBOR data items.
+//!
+//! This module is only available with the `alloc` feature.
+
+use super::descriptor::{Decoded, IDENTIFIER};
+use super::extref::EXTERNAL_REFERENCE;
+use alloc::string::String;
+use alloc::vec::Vec;
+use minicbor::data::Type;
+use minicbor::decode::{Decode, Decoder, Error};
+
+/// A decoded CBOR data item of any type.
+///
+/// A value of this type is useful when decoding a method call result whose schema is not known
+/// in advance. Every nested array and map is decoded recursively.
+///
+/// Opaque value descriptors (integers with the Identifier tag) are decoded as
+/// [`Descriptor`](Value::Descriptor), which holds a [`Decoded`](Decoded) rather than an
+/// [`Owned`](super::descriptor::Owned). This is because decoding is safe and can be applied to
+/// any bytes, while taking ownership of a descriptor is only sound for the genuine result of a
+/// method call; the caller must convert each descriptor with
+/// [`Decoded::into_owned`](Decoded::into_owned) after checking that this is the case.
+///
+/// External References (the tag used by [`extref`](super::extref) to pass application memory into
+/// a method call) are rejected with a decode error, since they are only meaningful as method
+/// parameters and should never appear in a result.
+///
+/// Arrays, maps, and tags may be nested at most [`MAX_DEPTH`](Value::MAX_DEPTH) levels deep;
+/// deeper data is rejected with a decode error rather than exhausting the stack.
+#[derive(Debug, PartialEq)]
+pub enum Value {
+	/// A null or undefined value.
+	Null,
+
+	/// A boolean.
+	Bool(bool),
+
+	/// An integer.
+	Int(i64),
+
+	/// A floating-point number.
+	Float(f64),
+
+	/// A UTF-8 string.
+	Str(String),
+
+	/// A byte string.
+	Bytes(Vec<u8>),
+
+	/// An array of values.
+	Array(Vec<Value>),
+
+	/// A map, as a list of key/value pairs in the order they were encoded.
+	Map(Vec<(Value, Value)>),
+
+	/// An opaque value descriptor.
+	Descriptor(Decoded),
+}
+
+impl Value {
+	/// The maximum number of arrays, maps, and tags that may enclose a decoded value.
+	pub const MAX_DEPTH: usize = 64;
+
+	/// Decodes a value that may be enclosed by at most `depth` more arrays, maps, and tags.
+	///
+	/// # Errors
+	/// A decode error is returned if the data is not a supported CBOR data item or is nested too
+	/// deeply.
+	fn decode_nested(d: &mut Decoder<'_>, depth: usize) -> Result<Self, Error> {
+		let position = d.position();
+		let nested = |depth: usize| {
+			depth
+				.checked_sub(1)
+				.ok_or_else(|| Error::message("CBOR data nested too deeply").at(position))
+		};
+		match d.datatype()? {
+			Type::Null | Type::Undefined => {
+				d.skip()?;
+				Ok(Self::Null)
+			}
+			Type::Bool => Ok(Self::Bool(d.bool()?)),
+			Type::U8
+			| Type::U16
+			| Type::U32
+			| Type::U64
+			| Type::I8
+			| Type::I16
+			| Type::I32
+			| Type::I64
+			| Type::Int => Ok(Self::Int(d.i64()?)),
+			Type::F16 | Type::F32 | Type::F64 => Ok(Self::Float(d.f64()?)),
+			Type::String | Type::StringIndef => {
+				let mut s = String::new();
+				for chunk in d.str_iter()? {
+					s.push_str(chunk?);
+				}
+				Ok(Self::Str(s))
+			}
+			Type::Bytes | Type::BytesIndef => {
+				let mut b = Vec::new();
+				for chunk in d.bytes_iter()? {
+					b.extend_from_slice(chunk?);
+				}
+				Ok(Self::Bytes(b))
+			}
+			Type::Array | Type::ArrayIndef => {
+				let depth = nested(depth)?;
+				let len = d.array()?;
+				let mut items = Vec::new();
+				while Self::has_next(d, len, items.len())? {
+					items.push(Self::decode_nested(d, depth)?);
+				}
+				Ok(Self::Array(items))
+			}
+			Type::Map | Type::MapIndef => {
+				let depth = nested(depth)?;
+				let len = d.map()?;
+				let mut entries = Vec::new();
+				while Self::has_next(d, len, entries.len())? {
+					let key = Self::decode_nested(d, depth)?;
+					let value = Self::decode_nested(d, depth)?;
+					entries.push((key, value));
+				}
+				Ok(Self::Map(entries))
+			}
+			Type::Tag => {
+				let tag = d.tag()?;
+				if tag == IDENTIFIER {
+					d.set_position(position);
+					Ok(Self::Descriptor(d.decode()?))
+				} else if tag == EXTERNAL_REFERENCE {
+					// External references only ever flow from the application into OC-Wasm; one
+					// coming back out would refer to memory the decoder knows nothing about.
+					Err(Error::message("unexpected External Reference tag").at(position))
+				} else {
+					// Other tags (such as Binary UUID) only annotate the enclosed value.
+					Self::decode_nested(d, nested(depth)?)
+				}
+			}
+			other => Err(Error::type_mismatch(other).with_message("unsupported CBOR type")),
+		}
+	}
+
+	/// Returns whether an array or map has another element to decode.
+	///
+	/// The `len` parameter is the length of the array or map, or `None` if it is
+	/// indefinite-length, and `decoded` is the number of elements decoded so far. If an
+	/// indefinite-length array or map has ended, its terminating break is consumed.
+	///
+	/// # Errors
+	/// A decode error is returned if the data ends before the array or map does.
+	fn has_next(d: &mut Decoder<'_>, len: Option<u64>, decoded: usize) -> Result<bool, Error> {
+		match len {
+			Some(len) => Ok((decoded as u64) < len),
+			None => {
+				if d.datatype()? == Type::Break {
+					// A break is a single byte.
+					d.set_position(d.position() + 1);
+					Ok(false)
+				} else {
+					Ok(true)
+				}
+			}
+		}
+	}
+}
+
+impl<'b, Context> Decode<'b, Context> for Value {
+	fn decode(d: &mut Decoder<'b>, _: &mut Context) -> Result<Self, Error> {
+		Self::decode_nested(d, Self::MAX_DEPTH)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloc::vec;
+
+	/// Returns `depth` single-element arrays nested around an empty array.
+	fn nested_arrays(depth: usize) -> Vec<u8> {
+		let mut buffer = vec![0x81; depth];
+		buffer.push(0x80);
+		buffer
+	}
+
+	#[test]
+	fn decode_at_max_depth() {
+		let buffer = nested_arrays(Value::MAX_DEPTH - 1);
+		assert!(minicbor::decode::<Value>(&buffer).is_ok());
+	}
+
+	#[test]
+	fn decode_rejects_too_deep() {
+		let buffer = nested_arrays(Value::MAX_DEPTH);
+		assert!(minicbor::decode::<Value>(&buffer).is_err());
+		let buffer = nested_arrays(100_000);
+		assert!(minicbor::decode::<Value>(&buffer).is_err());
+	}
+
+	#[test]
+	fn decode_rejects_deep_tags() {
+		// Tag 37 (Binary UUID) repeated around a null.
+		let mut buffer = vec![0xD8, 0x25].repeat(Value::MAX_DEPTH + 1);
+		buffer.push(0xF6);
+		assert!(minicbor::decode::<Value>(&buffer).is_err());
+	}
+
+	#[test]
+	fn decode_nested_descriptor() {
+		// [39(5), {"d": 39(7)}]
+		let mut buffer = [0_u8; 10];
+		let mut encoder = minicbor::Encoder::new(&mut buffer[..]);
+		encoder
+			.array(2)
+			.unwrap()
+			.tag(IDENTIFIER)
+			.unwrap()
+			.u32(5)
+			.unwrap()
+			.map(1)
+			.unwrap()
+			.str("d")
+			.unwrap()
+			.tag(IDENTIFIER)
+			.unwrap()
+			.u32(7)
+			.unwrap();
+		let Value::Array(items) = minicbor::decode::<Value>(&buffer).unwrap() else {
+			panic!("expected an array");
+		};
+		let [Value::Descriptor(first), Value::Map(entries)] = &items[..] else {
+			panic!("expected a descriptor and a map, got {items:?}");
+		};
+		assert_eq!(first.as_raw(), 5);
+		let [(Value::Str(key), Value::Descriptor(second))] = &entries[..] else {
+			panic!("expected one descriptor entry, got {entries:?}");
+		};
+		assert_eq!(key, "d");
+		assert_eq!(second.as_raw(), 7);
+	}
+
+	#[test]
+	fn decode_rejects_external_reference() {
+		// 32769([2, 0, 0])
+		let reference = [0xD9, 0x80, 0x01, 0x83, 0x02, 0x00, 0x00];
+		assert!(minicbor::decode::<Value>(&reference).is_err());
+		// [1, 32769([2, 0, 0])]
+		let mut buffer = vec![0x82, 0x01];
+		buffer.extend_from_slice(&reference);
+		assert!(minicbor::decode::<Value>(&buffer).is_err());
+		// {"r": 32769([2, 0, 0])}
+		let mut buffer = vec![0xA1, 0x61, b'r'];
+		buffer.extend_from_slice(&reference);
+		assert!(minicbor::decode::<Value>(&buffer).is_err());
+	}
+
+	#[test]
+	fn decode_indefinite() {
+		// [_ 1, {_ "a": null}]
+		let buffer = [0x9F, 0x01, 0xBF, 0x61, b'a', 0xF6, 0xFF, 0xFF];
+		assert_eq!(
+			minicbor::decode::<Value>(&buffer).unwrap(),
+			Value::Array(vec![
+				Value::Int(1),
+				Value::Map(vec![(Value::Str("a".into()), Value::Null)]),
+			])
+		);
+	}
+}